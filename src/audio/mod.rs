@@ -0,0 +1,96 @@
+//! Music and sound effects, mixed through master, music and SFX volume buses.
+//! Nothing is played unless Bevy's audio plugin is present, so the game still
+//! runs headless.
+
+pub mod music;
+pub mod sfx;
+pub mod spatial;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::save::DataDir;
+
+const SETTINGS_PATH: &str = "settings.json";
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DataDir>();
+        app.init_resource::<AudioSettings>();
+        app.add_plugins(music::MusicPlugin);
+        app.add_plugins(sfx::SfxPlugin);
+        app.add_plugins(spatial::SpatialAudioPlugin);
+        app.add_systems(Startup, load_settings);
+        app.add_systems(Last, write_settings);
+    }
+}
+
+/// Volume buses, each from 0 to 1. Changes are written to `settings.json`.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.0,
+            music: 0.1,
+            sfx: 0.5,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(dir.join(SETTINGS_PATH))?;
+        serde_json::from_str(&contents).map_err(io::Error::from)
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::from)?;
+        fs::write(dir.join(SETTINGS_PATH), contents)
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        (self.master * self.music).clamp(0.0, 1.0)
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        (self.master * self.sfx).clamp(0.0, 1.0)
+    }
+}
+
+/// Whether sounds can be played at all; false in headless apps.
+pub fn audio_available(sources: Option<Res<Assets<AudioSource>>>) -> bool {
+    sources.is_some()
+}
+
+fn load_settings(data_dir: Res<DataDir>, mut settings: ResMut<AudioSettings>) {
+    match AudioSettings::load(&data_dir.0) {
+        Ok(loaded) => *settings = loaded,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => warn!("Ignoring settings {}: {}", SETTINGS_PATH, error),
+    }
+}
+
+fn write_settings(data_dir: Res<DataDir>, settings: Res<AudioSettings>) {
+    // Loading counts as a change too, but only edits need writing
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    match settings.save(&data_dir.0) {
+        Ok(()) => info!("Settings saved"),
+        Err(error) => error!("Could not write {}: {}", SETTINGS_PATH, error),
+    }
+}
@@ -0,0 +1,185 @@
+//! Picks the music for the selected level, crossfades between tracks and
+//! ducks the music while a stinger plays over it.
+
+use bevy::asset::LoadState;
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+
+use crate::audio::{audio_available, AudioSettings};
+use crate::level_settings::ActiveLevelSettings;
+
+/// Played until a level names its own track.
+pub const DEFAULT_TRACK: &str = "audio/theme.ogg";
+pub const SECRET_JINGLE: &str = "audio/secret.wav";
+const CROSSFADE_SECONDS: f32 = 1.5;
+// Music gain while a stinger plays, reached over `DUCK_SECONDS`
+const DUCKED_GAIN: f32 = 0.3;
+const DUCK_SECONDS: f32 = 0.25;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayStinger>();
+        app.init_resource::<MusicDirector>();
+        app.add_systems(
+            Update,
+            (choose_track, play_stingers, drop_failed_stingers, mix_music)
+                .chain()
+                .run_if(audio_available),
+        );
+    }
+}
+
+/// A short cue, like a boss intro, played once over the music.
+#[derive(Event, Debug, Clone)]
+pub struct PlayStinger(pub String);
+
+#[derive(Resource, Debug)]
+pub struct MusicDirector {
+    /// Asset path of the track fading in or playing
+    pub current: Option<String>,
+    duck: f32,
+}
+
+impl Default for MusicDirector {
+    fn default() -> Self {
+        MusicDirector {
+            current: None,
+            duck: 1.0,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct MusicTrack {
+    /// Crossfade gain, from 0 to 1
+    gain: f32,
+    fading_out: bool,
+}
+
+/// Asset path of the stinger, so the same one never overlaps itself.
+#[derive(Component, Debug)]
+struct Stinger(String);
+
+fn choose_track(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    active_level: Res<ActiveLevelSettings>,
+    mut director: ResMut<MusicDirector>,
+    mut tracks: Query<&mut MusicTrack>,
+) {
+    // Levels without a track of their own keep the one of the area they were entered from
+    let wanted = active_level
+        .0
+        .music
+        .clone()
+        .or_else(|| director.current.clone())
+        .unwrap_or_else(|| DEFAULT_TRACK.to_string());
+
+    if director.current.as_ref() == Some(&wanted) {
+        return;
+    }
+
+    debug!("Music: {}", wanted);
+    for mut track in tracks.iter_mut() {
+        track.fading_out = true;
+    }
+
+    commands.spawn((
+        AudioPlayer::<AudioSource>(asset_server.load(wanted.clone())),
+        PlaybackSettings::LOOP.with_volume(Volume::new(0.0)),
+        MusicTrack {
+            gain: 0.0,
+            fading_out: false,
+        },
+    ));
+    director.current = Some(wanted);
+}
+
+fn play_stingers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<AudioSettings>,
+    mut events: EventReader<PlayStinger>,
+    playing: Query<&Stinger>,
+) {
+    let mut started: Vec<&String> = Vec::new();
+    for PlayStinger(path) in events.read() {
+        if started.contains(&path) || playing.iter().any(|stinger| stinger.0 == *path) {
+            continue;
+        }
+        started.push(path);
+
+        debug!("Stinger: {}", path);
+        commands.spawn((
+            AudioPlayer::<AudioSource>(asset_server.load(path.clone())),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new(settings.music_volume())),
+            Stinger(path.clone()),
+        ));
+    }
+}
+
+/// A stinger that can't load would otherwise keep the music ducked forever.
+fn drop_failed_stingers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stingers: Query<(Entity, &AudioPlayer), With<Stinger>>,
+) {
+    for (entity, player) in stingers.iter() {
+        if matches!(asset_server.load_state(&player.0), LoadState::Failed(_)) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn mix_music(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<AudioSettings>,
+    mut director: ResMut<MusicDirector>,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    stingers: Query<&AudioSink, With<Stinger>>,
+) {
+    let dt = time.delta_secs();
+
+    let ducking = stingers.iter().any(|sink| !sink.empty());
+    let duck_target = if ducking { DUCKED_GAIN } else { 1.0 };
+    let duck_step = (1.0 - DUCKED_GAIN) * dt / DUCK_SECONDS;
+    director.duck = move_towards(director.duck, duck_target, duck_step);
+
+    for (entity, mut track, sink) in tracks.iter_mut() {
+        let target = if track.fading_out { 0.0 } else { 1.0 };
+        track.gain = move_towards(track.gain, target, dt / CROSSFADE_SECONDS);
+
+        if track.fading_out && track.gain == 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        if let Some(sink) = sink {
+            sink.set_volume(track.gain * director.duck * settings.music_volume());
+        }
+    }
+}
+
+fn move_towards(current: f32, target: f32, step: f32) -> f32 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn built_in_tracks_are_shipped() {
+        for path in [DEFAULT_TRACK, SECRET_JINGLE] {
+            assert!(Path::new("assets").join(path).exists(), "missing {}", path);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Sound effects for gameplay events. Which files play for which event, and
+//! how much they vary, comes from `assets/audio/sounds.sfx.json`.
+//! `SfxPlayed` is sent for every sound started, with or without an audio device.
+
+use std::collections::HashMap;
+
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::audio::{audio_available, AudioSettings};
+use crate::character::{DoubleJumped, Jumped, Landed};
+use crate::health::Hurt;
+use crate::item::ItemPicked;
+use crate::json_asset::JsonAssetPlugin;
+
+const SFX_TABLE: &str = "audio/sounds.sfx.json";
+
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<SfxTable>::new(&["sfx.json"]));
+        app.add_event::<SfxPlayed>();
+        app.init_resource::<Voices>();
+        app.add_systems(Startup, load_sfx_table);
+        app.add_systems(
+            Update,
+            (
+                start_sounds,
+                spawn_voices.run_if(audio_available),
+                forget_finished_voices,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sound {
+    Jump,
+    DoubleJump,
+    Land,
+    ItemPicked,
+    Hurt,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SoundSpec {
+    /// Asset paths, one picked at random each time
+    pub files: Vec<String>,
+    pub volume: f32,
+    /// Volume is scaled by up to this fraction either way
+    pub volume_variation: f32,
+    /// Playback speed, and so pitch, is scaled by up to this fraction either way
+    pub pitch_variation: f32,
+    /// Further requests are dropped while this many are playing
+    pub max_concurrent: usize,
+}
+
+impl Default for SoundSpec {
+    fn default() -> Self {
+        SoundSpec {
+            files: Vec::new(),
+            volume: 1.0,
+            volume_variation: 0.0,
+            pitch_variation: 0.0,
+            max_concurrent: 4,
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone, Default)]
+pub struct SfxTable(pub HashMap<Sound, SoundSpec>);
+
+impl SfxTable {
+    /// What to play for `sound` with `playing` copies already playing, if anything.
+    pub fn pick(&self, sound: Sound, playing: usize, rng: &mut impl Rng) -> Option<SfxPlayed> {
+        let spec = self.0.get(&sound)?;
+        if spec.files.is_empty() || playing >= spec.max_concurrent {
+            return None;
+        }
+
+        Some(SfxPlayed {
+            sound,
+            path: spec.files[rng.gen_range(0..spec.files.len())].clone(),
+            volume: spec.volume * vary(rng, spec.volume_variation),
+            speed: vary(rng, spec.pitch_variation),
+        })
+    }
+}
+
+/// A factor within `amount` of 1.
+fn vary(rng: &mut impl Rng, amount: f32) -> f32 {
+    if amount > 0.0 {
+        1.0 + rng.gen_range(-amount..=amount)
+    } else {
+        1.0
+    }
+}
+
+/// A sound effect that started, before the SFX bus is applied.
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct SfxPlayed {
+    pub sound: Sound,
+    pub path: String,
+    pub volume: f32,
+    pub speed: f32,
+}
+
+#[derive(Resource)]
+struct SfxTableHandle(Handle<SfxTable>);
+
+/// Copies of each sound playing right now, for the concurrency limits.
+#[derive(Resource, Default)]
+struct Voices(HashMap<Sound, usize>);
+
+#[derive(Component)]
+struct SfxVoice(Sound);
+
+fn load_sfx_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SfxTableHandle(asset_server.load(SFX_TABLE)));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_sounds(
+    table: Res<SfxTableHandle>,
+    tables: Res<Assets<SfxTable>>,
+    voices: Res<Voices>,
+    mut jumped: EventReader<Jumped>,
+    mut double_jumped: EventReader<DoubleJumped>,
+    mut landed: EventReader<Landed>,
+    mut item_picked: EventReader<ItemPicked>,
+    mut hurt: EventReader<Hurt>,
+    mut played: EventWriter<SfxPlayed>,
+) {
+    let sounds: Vec<Sound> = jumped
+        .read()
+        .map(|_| Sound::Jump)
+        .chain(double_jumped.read().map(|_| Sound::DoubleJump))
+        .chain(landed.read().map(|_| Sound::Land))
+        .chain(item_picked.read().map(|_| Sound::ItemPicked))
+        .chain(hurt.read().map(|_| Sound::Hurt))
+        .collect();
+
+    let Some(table) = tables.get(&table.0) else {
+        return;
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut started: HashMap<Sound, usize> = HashMap::new();
+    for sound in sounds {
+        let playing =
+            voices.0.get(&sound).copied().unwrap_or(0) + started.get(&sound).copied().unwrap_or(0);
+        if let Some(sfx) = table.pick(sound, playing, &mut rng) {
+            *started.entry(sound).or_default() += 1;
+            played.send(sfx);
+        }
+    }
+}
+
+fn spawn_voices(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<AudioSettings>,
+    mut played: EventReader<SfxPlayed>,
+) {
+    for sfx in played.read() {
+        commands.spawn((
+            AudioPlayer::<AudioSource>(asset_server.load(sfx.path.clone())),
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(sfx.volume * settings.sfx_volume()))
+                .with_speed(sfx.speed),
+            SfxVoice(sfx.sound),
+        ));
+    }
+}
+
+fn forget_finished_voices(mut voices: ResMut<Voices>, playing: Query<&SfxVoice>) {
+    voices.0.clear();
+    for voice in playing.iter() {
+        *voices.0.entry(voice.0).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn table() -> SfxTable {
+        serde_json::from_str(
+            r#"{
+                "jump": {
+                    "files": ["audio/sfx/jump1.ogg", "audio/sfx/jump2.ogg"],
+                    "volume": 0.8,
+                    "volume_variation": 0.1,
+                    "pitch_variation": 0.2,
+                    "max_concurrent": 2
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn variation_stays_in_range() {
+        let table = table();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..100 {
+            let sfx = table.pick(Sound::Jump, 0, &mut rng).unwrap();
+            assert!(sfx.path.starts_with("audio/sfx/jump"));
+            assert!((0.72..=0.88).contains(&sfx.volume));
+            assert!((0.8..=1.2).contains(&sfx.speed));
+        }
+    }
+
+    #[test]
+    fn concurrency_limit_drops_extra_sounds() {
+        let table = table();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        assert!(table.pick(Sound::Jump, 1, &mut rng).is_some());
+        assert!(table.pick(Sound::Jump, 2, &mut rng).is_none());
+    }
+
+    #[test]
+    fn sounds_missing_from_the_table_are_silent() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!(table().pick(Sound::Hurt, 0, &mut rng).is_none());
+    }
+
+    #[test]
+    fn shipped_table_only_names_shipped_files() {
+        let table: SfxTable =
+            serde_json::from_str(include_str!("../../assets/audio/sounds.sfx.json")).unwrap();
+
+        assert!(!table.0.is_empty());
+        for spec in table.0.values() {
+            for file in &spec.files {
+                let path = std::path::Path::new("assets").join(file);
+                assert!(path.exists(), "missing {}", path.display());
+            }
+        }
+    }
+}
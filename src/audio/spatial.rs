@@ -0,0 +1,264 @@
+//! Looping sounds placed in the world, heard from the `Camera2d`. Bevy pans
+//! them between the listener's ears; on top of that they fade out towards
+//! the edge of their range and get quieter behind walls of the `Collisions` layer.
+//! Levels place them with `SoundEmitter` entities.
+
+use std::collections::HashSet;
+
+use bevy::audio::{AudioSinkPlayback, DefaultSpatialScale, SpatialScale, Volume};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::audio::{audio_available, AudioSettings};
+use crate::level::LevelIndex;
+use crate::navigation::{is_solid, NavGraphs};
+use crate::registry;
+
+// World pixels per unit of distance in the audio backend
+const PIXELS_PER_UNIT: f32 = 64.0;
+const EAR_GAP: f32 = 32.0;
+// Volume kept for every wall cell between emitter and listener, and the floor
+const OCCLUSION_PER_WALL: f32 = 0.6;
+const MIN_OCCLUSION: f32 = 0.15;
+// Fraction of the range over which emitters fade out
+const RANGE_FADE: f32 = 0.25;
+const OCCLUSION_STEP: f32 = 4.0;
+
+pub struct SpatialAudioPlugin;
+
+impl Plugin for SpatialAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DefaultSpatialScale(SpatialScale::new_2d(
+            1.0 / PIXELS_PER_UNIT,
+        )));
+        app.register_ldtk_entity::<SoundEmitterBundle>(registry::SOUND_EMITTER);
+        app.add_systems(
+            Update,
+            (add_listener, start_emitters, mix_emitters)
+                .chain()
+                .run_if(audio_available),
+        );
+    }
+}
+
+/// Plays `path` on a loop from this entity's position.
+#[derive(Component, Clone, Debug)]
+pub struct SoundEmitter {
+    pub path: String,
+    pub volume: f32,
+    /// Silent beyond this many pixels from the listener
+    pub range: f32,
+}
+
+impl SoundEmitter {
+    pub fn new(path: impl Into<String>) -> Self {
+        SoundEmitter {
+            path: path.into(),
+            volume: 1.0,
+            range: 320.0,
+        }
+    }
+
+    fn from_fields(entity_instance: &EntityInstance) -> Self {
+        let defaults = SoundEmitter::new(
+            entity_instance
+                .get_string_field("sound")
+                .cloned()
+                .unwrap_or_default(),
+        );
+        SoundEmitter {
+            volume: entity_instance
+                .get_float_field("volume")
+                .copied()
+                .unwrap_or(defaults.volume),
+            range: entity_instance
+                .get_float_field("range")
+                .copied()
+                .unwrap_or(defaults.range),
+            ..defaults
+        }
+    }
+}
+
+impl Default for SoundEmitter {
+    fn default() -> Self {
+        SoundEmitter::new(String::new())
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct SoundEmitterBundle {
+    #[with(SoundEmitter::from_fields)]
+    emitter: SoundEmitter,
+}
+
+#[derive(Component)]
+struct EmitterVoice;
+
+fn add_listener(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<Camera2d>, Without<SpatialListener>)>,
+) {
+    for camera in cameras.iter() {
+        commands
+            .entity(camera)
+            .insert(SpatialListener::new(EAR_GAP));
+    }
+}
+
+fn start_emitters(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    emitters: Query<(Entity, &SoundEmitter), Added<SoundEmitter>>,
+) {
+    for (entity, emitter) in emitters.iter() {
+        commands.entity(entity).with_child((
+            AudioPlayer::<AudioSource>(asset_server.load(emitter.path.clone())),
+            // Starts silent until the first mix knows how far away it is
+            PlaybackSettings::LOOP
+                .with_spatial(true)
+                .with_volume(Volume::new(0.0)),
+            Transform::default(),
+            EmitterVoice,
+        ));
+    }
+}
+
+/// 1 up close, falling to 0 over the last `RANGE_FADE` of the range.
+fn range_gain(distance: f32, range: f32) -> f32 {
+    let fade_start = range * (1.0 - RANGE_FADE);
+    if distance <= fade_start {
+        1.0
+    } else {
+        (1.0 - (distance - fade_start) / (range - fade_start)).max(0.0)
+    }
+}
+
+/// Volume factor for the solid cells crossed going from `from` to `to`.
+fn occlusion(from: Vec2, to: Vec2, level_index: &LevelIndex, nav_graphs: &NavGraphs) -> f32 {
+    let steps = (from.distance(to) / OCCLUSION_STEP).ceil() as usize;
+    let mut walls: HashSet<(String, GridCoords)> = HashSet::new();
+
+    for step in 1..steps {
+        let point = from.lerp(to, step as f32 / steps as f32);
+        let Some(level) = level_index.at(point).next() else {
+            continue;
+        };
+        let Some(graph) = nav_graphs.get(&LevelIid::new(level.iid.clone())) else {
+            continue;
+        };
+
+        let coords = graph.coords_at(point - level.rect.min);
+        if is_solid(graph.value(coords)) {
+            walls.insert((level.iid.clone(), coords));
+        }
+    }
+
+    OCCLUSION_PER_WALL
+        .powi(walls.len() as i32)
+        .max(MIN_OCCLUSION)
+}
+
+fn mix_emitters(
+    settings: Res<AudioSettings>,
+    level_index: Res<LevelIndex>,
+    nav_graphs: Res<NavGraphs>,
+    listeners: Query<&GlobalTransform, With<SpatialListener>>,
+    emitters: Query<(&SoundEmitter, &GlobalTransform)>,
+    voices: Query<(&Parent, &SpatialAudioSink), With<EmitterVoice>>,
+) {
+    let Ok(listener) = listeners.get_single() else {
+        return;
+    };
+    let listener = listener.translation().xy();
+
+    for (parent, sink) in voices.iter() {
+        let Ok((emitter, transform)) = emitters.get(parent.get()) else {
+            continue;
+        };
+        let position = transform.translation().xy();
+
+        let distance = listener.distance(position);
+        let mut gain = range_gain(distance, emitter.range);
+        if gain > 0.0 {
+            gain *= occlusion(listener, position, &level_index, &nav_graphs);
+        }
+
+        sink.set_volume(emitter.volume * gain * settings.sfx_volume());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::NavGraph;
+    use bevy_ecs_ldtk::ldtk::{LdtkJson, Level};
+
+    #[test]
+    fn range_gain_fades_over_the_end_of_the_range() {
+        assert_eq!(range_gain(0.0, 320.0), 1.0);
+        assert_eq!(range_gain(240.0, 320.0), 1.0);
+        assert_eq!(range_gain(280.0, 320.0), 0.5);
+        assert_eq!(range_gain(320.0, 320.0), 0.0);
+        assert_eq!(range_gain(1000.0, 320.0), 0.0);
+    }
+
+    /// One 128x64 level at the origin, with one wall cell in the second row,
+    /// two in the third and six in the bottom one.
+    fn room() -> (LevelIndex, NavGraphs) {
+        let level_index = LevelIndex::from_project(&LdtkJson {
+            levels: vec![Level {
+                iid: "room".to_string(),
+                identifier: "Room".to_string(),
+                px_wid: 128,
+                px_hei: 64,
+                ..default()
+            }],
+            ..default()
+        });
+
+        #[rustfmt::skip]
+        let csv = [
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 1, 0, 0, 0, 0,
+            0, 1, 1, 0, 0, 0, 0, 0,
+            0, 1, 1, 1, 1, 1, 1, 0,
+        ];
+        let mut nav_graphs = NavGraphs::default();
+        nav_graphs.insert(
+            LevelIid::new("room"),
+            NavGraph::from_int_grid(8, 4, 16, &csv),
+        );
+
+        (level_index, nav_graphs)
+    }
+
+    /// Occlusion straight across the room at height `y`.
+    fn across(y: f32) -> f32 {
+        let (level_index, nav_graphs) = room();
+        occlusion(
+            Vec2::new(8.0, y),
+            Vec2::new(120.0, y),
+            &level_index,
+            &nav_graphs,
+        )
+    }
+
+    #[test]
+    fn each_wall_crossed_muffles_the_sound() {
+        // The level spans y = -64 to 0, with the top row first in the csv
+        assert_eq!(across(-8.0), 1.0);
+        assert_eq!(across(-24.0), OCCLUSION_PER_WALL);
+        assert_eq!(across(-40.0), OCCLUSION_PER_WALL.powi(2));
+    }
+
+    #[test]
+    fn occlusion_has_a_floor() {
+        assert_eq!(across(-56.0), MIN_OCCLUSION);
+    }
+
+    #[test]
+    fn nothing_outside_the_levels_occludes() {
+        assert_eq!(across(40.0), 1.0);
+    }
+}
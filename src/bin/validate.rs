@@ -0,0 +1,298 @@
+//! Checks an LDtk project against what the game registers, or prints how its
+//! levels connect.
+//!
+//! Usage:
+//!     cargo run --bin validate -- assets/gridvania.ldtk
+//!     cargo run --bin validate -- graph assets/gridvania.ldtk [--json]
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::process::ExitCode;
+
+use bevy_ecs_ldtk::ldtk::{FieldValue, LdtkJson, Level, WorldLayout};
+use bevy_playground::level_graph::LevelGraph;
+use bevy_playground::registry;
+
+// Optional bool level field restricting the reachability check to some levels
+const REACHABLE_FIELD: &str = "reachable";
+
+#[derive(Default)]
+struct Report {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Report {
+    fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    fn warning(&mut self, message: String) {
+        self.warnings.push(message);
+    }
+}
+
+fn load(path: &str) -> Result<LdtkJson, String> {
+    let contents = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    serde_json::from_str(&contents).map_err(|error| error.to_string())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["graph", path, rest @ ..] => print_graph(path, rest.contains(&"--json")),
+        [path] => run_validation(path),
+        _ => {
+            eprintln!("usage: validate <project.ldtk>");
+            eprintln!("       validate graph <project.ldtk> [--json]");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_graph(path: &str, json: bool) -> ExitCode {
+    let project = match load(path) {
+        Ok(project) => project,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let graph = LevelGraph::from_project(&project);
+    if json {
+        println!("{}", graph.to_json());
+    } else {
+        print!("{}", graph.to_dot());
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn run_validation(path: &str) -> ExitCode {
+    let project = match load(path) {
+        Ok(project) => project,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path, error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let report = validate(&project);
+
+    for warning in &report.warnings {
+        println!("warning: {}", warning);
+    }
+    for error in &report.errors {
+        println!("error: {}", error);
+    }
+    println!(
+        "{}: {} error(s), {} warning(s)",
+        path,
+        report.errors.len(),
+        report.warnings.len()
+    );
+
+    if report.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn all_levels(project: &LdtkJson) -> Vec<&Level> {
+    project
+        .levels
+        .iter()
+        .chain(project.worlds.iter().flat_map(|world| world.levels.iter()))
+        .collect()
+}
+
+fn validate(project: &LdtkJson) -> Report {
+    let mut report = Report::default();
+    let levels = all_levels(project);
+
+    check_entity_identifiers(project, &mut report);
+    check_int_grid_values(project, &mut report);
+    check_entity_refs(&levels, &mut report);
+    check_reachability(project, &levels, &mut report);
+
+    report
+}
+
+fn check_entity_identifiers(project: &LdtkJson, report: &mut Report) {
+    let defined: HashSet<&str> = project
+        .defs
+        .entities
+        .iter()
+        .map(|entity| entity.identifier.as_str())
+        .collect();
+
+    for identifier in registry::ENTITIES {
+        if !defined.contains(identifier) {
+            report.error(format!(
+                "entity `{}` is registered by the game but not defined in the project",
+                identifier
+            ));
+        }
+    }
+
+    for identifier in defined {
+        if !registry::ENTITIES.contains(&identifier) {
+            report.warning(format!(
+                "entity `{}` is defined in the project but the game does not register it",
+                identifier
+            ));
+        }
+    }
+}
+
+fn check_int_grid_values(project: &LdtkJson, report: &mut Report) {
+    let defined: BTreeSet<i32> = project
+        .defs
+        .layers
+        .iter()
+        .flat_map(|layer| layer.int_grid_values.iter().map(|value| value.value))
+        .collect();
+
+    for value in registry::INT_GRID_VALUES {
+        if !defined.contains(value) {
+            report.error(format!(
+                "IntGrid value {} is registered by the game but no layer defines it",
+                value
+            ));
+        }
+    }
+
+    for value in defined {
+        if !registry::INT_GRID_VALUES.contains(&value) {
+            report.warning(format!(
+                "IntGrid value {} is defined in the project but the game does not register it",
+                value
+            ));
+        }
+    }
+}
+
+fn check_entity_refs(levels: &[&Level], report: &mut Report) {
+    let entity_iids: HashSet<&str> = levels
+        .iter()
+        .flat_map(|level| level.layer_instances.iter().flatten())
+        .flat_map(|layer| layer.entity_instances.iter())
+        .map(|entity| entity.iid.as_str())
+        .collect();
+
+    for level in levels {
+        for entity in level
+            .layer_instances
+            .iter()
+            .flatten()
+            .flat_map(|layer| layer.entity_instances.iter())
+        {
+            for field in &entity.field_instances {
+                let references = match &field.value {
+                    FieldValue::EntityRef(reference) => vec![reference],
+                    FieldValue::EntityRefs(references) => references.iter().collect(),
+                    _ => continue,
+                };
+
+                for reference in references.into_iter().flatten() {
+                    if !entity_iids.contains(reference.entity_iid.as_str()) {
+                        report.error(format!(
+                            "[{}] field `{}` of {} {} points at missing entity {}",
+                            level.identifier,
+                            field.identifier,
+                            entity.identifier,
+                            entity.iid,
+                            reference.entity_iid
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_marked_reachable(level: &Level) -> Option<bool> {
+    level
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == REACHABLE_FIELD)
+        .map(|field| matches!(field.value, FieldValue::Bool(true)))
+}
+
+fn check_reachability(project: &LdtkJson, levels: &[&Level], report: &mut Report) {
+    if project.world_layout != Some(WorldLayout::GridVania) {
+        report.warning("world layout is not GridVania, skipping reachability".to_string());
+        return;
+    }
+
+    if levels.iter().any(|level| level.layer_instances.is_none()) {
+        report.warning("levels are stored in separate files, skipping reachability".to_string());
+        return;
+    }
+
+    let graph = LevelGraph::from_project(project);
+    if !graph.levels.iter().any(|level| level.spawn) {
+        report.error(format!("no level has a `{}` entity", registry::PLAYER));
+        return;
+    }
+
+    let analysis = graph.analyze();
+
+    for index in analysis.unreachable {
+        // Without the field every level is expected to be reachable
+        if is_marked_reachable(levels[index]) == Some(false) {
+            continue;
+        }
+
+        report.error(format!(
+            "[{}] is not connected to any level with a `{}` spawn",
+            graph.levels[index].identifier,
+            registry::PLAYER
+        ));
+    }
+
+    for index in analysis.dead_ends {
+        report.warning(format!(
+            "[{}] is a one-way dead end: no way back to a spawn or an exit",
+            graph.levels[index].identifier
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = "assets/gridvania.ldtk";
+
+    #[test]
+    fn shipped_project_is_valid() {
+        let report = validate(&load(PROJECT).unwrap());
+
+        assert_eq!(report.errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_registered_entity_is_an_error() {
+        let mut project = load(PROJECT).unwrap();
+        project
+            .defs
+            .entities
+            .retain(|entity| entity.identifier != registry::ENEMY);
+
+        let report = validate(&project);
+
+        assert!(report
+            .errors
+            .iter()
+            .any(|error| error.contains(registry::ENEMY)));
+    }
+}
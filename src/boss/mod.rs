@@ -0,0 +1,277 @@
+//! Boss encounters. Entering a boss level seals it until the boss is beaten;
+//! the boss and its attack patterns come from `assets/bosses/*.boss.json`.
+
+pub mod script;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::audio::music::PlayStinger;
+use crate::audio::spatial::SoundEmitter;
+use crate::character::Character;
+use crate::health::{ContactDamage, Died, Health};
+use crate::json_asset::JsonAssetPlugin;
+use crate::level::{CurrentLevel, RestartRequested};
+use crate::physics::{self, layers, BodyKind, BodyVelocity};
+use crate::save::{SaveData, SaveRequested};
+use script::{BossAttack, BossScript};
+
+const DOOR_THICKNESS: f32 = 16.0;
+const BOSS_SCRIPTS: &[&str] = &["bosses/boss_room.boss.json"];
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<BossScript>::new(&["boss.json"]));
+        app.init_resource::<Encounter>();
+        app.add_systems(Startup, load_boss_scripts);
+        app.add_systems(Update, (start_encounter, reset_encounter_on_respawn));
+        app.add_systems(FixedUpdate, (run_boss_pattern, end_encounter).chain());
+    }
+}
+
+#[derive(Resource)]
+struct BossScripts(Vec<Handle<BossScript>>);
+
+/// The fight in progress, if any.
+#[derive(Resource, Default)]
+pub struct Encounter {
+    pub level: Option<String>,
+    boss: Option<Entity>,
+    doors: Vec<Entity>,
+}
+
+#[derive(Component)]
+pub struct ArenaDoor;
+
+#[derive(Component, Debug)]
+pub struct Boss {
+    script: Handle<BossScript>,
+    pub phase: usize,
+    step: usize,
+    elapsed: f32,
+}
+
+fn load_boss_scripts(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = BOSS_SCRIPTS
+        .iter()
+        .map(|path| asset_server.load(*path))
+        .collect();
+
+    commands.insert_resource(BossScripts(handles));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_encounter(
+    mut commands: Commands,
+    mut encounter: ResMut<Encounter>,
+    current_level: CurrentLevel,
+    scripts: Res<BossScripts>,
+    script_assets: Res<Assets<BossScript>>,
+    save: Res<SaveData>,
+    levels: Query<(&LevelIid, &Transform)>,
+    mut stingers: EventWriter<PlayStinger>,
+) {
+    if encounter.level.is_some() {
+        return;
+    }
+
+    let Some(level) = current_level.raw() else {
+        return;
+    };
+
+    if save.defeated_bosses.contains(&level.identifier) {
+        return;
+    }
+
+    let Some((handle, script)) = scripts.0.iter().find_map(|handle| {
+        script_assets
+            .get(handle)
+            .filter(|script| script.level == level.identifier)
+            .map(|script| (handle, script))
+    }) else {
+        return;
+    };
+
+    let Some((_, level_transform)) = levels
+        .iter()
+        .find(|(level_iid, _)| level_iid.as_str() == level.iid)
+    else {
+        return;
+    };
+
+    let min = level_transform.translation.xy();
+    let size = Vec2::new(level.px_wid as f32, level.px_hei as f32);
+    info!("Arena locked: {} in {}", script.name, level.identifier);
+    if let Some(intro) = &script.intro {
+        stingers.send(PlayStinger(intro.clone()));
+    }
+
+    // Seal every edge of the level just outside its bounds
+    let half = DOOR_THICKNESS / 2.0;
+    let doors = [
+        (
+            Vec2::new(min.x - half, min.y + size.y / 2.0),
+            Vec2::new(half, size.y / 2.0),
+        ),
+        (
+            Vec2::new(min.x + size.x + half, min.y + size.y / 2.0),
+            Vec2::new(half, size.y / 2.0),
+        ),
+        (
+            Vec2::new(min.x + size.x / 2.0, min.y - half),
+            Vec2::new(size.x / 2.0, half),
+        ),
+        (
+            Vec2::new(min.x + size.x / 2.0, min.y + size.y + half),
+            Vec2::new(size.x / 2.0, half),
+        ),
+    ];
+
+    encounter.doors = doors
+        .into_iter()
+        .map(|(center, half_size)| {
+            commands
+                .spawn((
+                    ArenaDoor,
+                    Sprite::from_color(Color::srgb(0.2, 0.2, 0.25), half_size * 2.0),
+                    Transform::from_xyz(center.x, center.y, 5.0),
+                    physics::cuboid(half_size.x, half_size.y),
+                    physics::body(BodyKind::Fixed),
+                    physics::friction(0.0),
+                    physics::collision_layers(layers::WORLD, layers::ALL),
+                ))
+                .id()
+        })
+        .collect();
+
+    let boss_size = Vec2::from(script.size);
+    let spawn = min + Vec2::from(script.spawn);
+    let mut boss = commands.spawn((
+        Boss {
+            script: handle.clone(),
+            phase: 0,
+            step: 0,
+            elapsed: 0.0,
+        },
+        Health::new(script.health),
+        ContactDamage(script.contact_damage),
+        Sprite::from_color(Color::srgb(0.6, 0.1, 0.1), boss_size),
+        Transform::from_xyz(spawn.x, spawn.y, 5.0),
+        physics::cuboid(boss_size.x / 2.0, boss_size.y / 2.0),
+        physics::velocity(),
+        physics::lock_rotation(),
+        physics::body(BodyKind::Dynamic),
+        physics::friction(0.0),
+        physics::report_collisions(),
+        physics::collision_layers(layers::ENEMY, layers::ALL),
+    ));
+    if let Some(ambient) = &script.ambient {
+        boss.insert(SoundEmitter::new(ambient.clone()));
+    }
+    encounter.boss = Some(boss.id());
+    encounter.level = Some(level.identifier.clone());
+}
+
+fn run_boss_pattern(
+    time: Res<Time>,
+    script_assets: Res<Assets<BossScript>>,
+    player: Query<&GlobalTransform, With<Character>>,
+    mut bosses: Query<(&mut Boss, &Health, &GlobalTransform, BodyVelocity)>,
+) {
+    let player_x = player
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation().x);
+
+    for (mut boss, health, transform, mut velocity) in bosses.iter_mut() {
+        let Some(script) = script_assets.get(&boss.script) else {
+            continue;
+        };
+
+        if health.is_dead() || script.phases.is_empty() {
+            velocity.set_linear(Vec2::ZERO);
+            continue;
+        }
+
+        let phase = script.phase_for(health.current as f32 / health.max as f32);
+        if phase != boss.phase {
+            info!("{} enters phase {}", script.name, script.phases[phase].name);
+            boss.phase = phase;
+            boss.step = 0;
+            boss.elapsed = 0.0;
+        }
+
+        let pattern = &script.phases[boss.phase].pattern;
+        let Some(attack) = pattern.get(boss.step) else {
+            continue;
+        };
+
+        let facing = player_x.map_or(1.0, |x| (x - transform.translation().x).signum());
+        let linear = velocity.linear();
+        match attack {
+            BossAttack::Wait { .. } => velocity.set_linear(Vec2::new(0.0, linear.y)),
+            BossAttack::Charge { speed, .. } => {
+                velocity.set_linear(Vec2::new(facing * speed, linear.y));
+            }
+            BossAttack::Leap {
+                speed_x, speed_y, ..
+            } => {
+                // Only push off the ground when the leap starts
+                if boss.elapsed == 0.0 {
+                    velocity.set_linear(Vec2::new(facing * speed_x, *speed_y));
+                }
+            }
+        }
+
+        boss.elapsed += time.delta_secs();
+        if boss.elapsed >= attack.duration() {
+            boss.step = (boss.step + 1) % pattern.len();
+            boss.elapsed = 0.0;
+        }
+    }
+}
+
+fn end_encounter(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    mut encounter: ResMut<Encounter>,
+    mut save: ResMut<SaveData>,
+    mut save_requests: EventWriter<SaveRequested>,
+) {
+    for Died(entity) in died.read() {
+        if encounter.boss != Some(*entity) {
+            continue;
+        }
+
+        for door in encounter.doors.drain(..) {
+            commands.entity(door).despawn_recursive();
+        }
+        commands.entity(*entity).despawn_recursive();
+        encounter.boss = None;
+
+        if let Some(level) = encounter.level.take() {
+            info!("Boss of {} defeated, arena unlocked", level);
+            save.defeated_bosses.insert(level);
+            save_requests.send(SaveRequested);
+        }
+    }
+}
+
+fn reset_encounter_on_respawn(
+    mut commands: Commands,
+    mut restart: EventReader<RestartRequested>,
+    mut encounter: ResMut<Encounter>,
+) {
+    // The fight starts over once the level is back
+    if restart.read().count() == 0 {
+        return;
+    }
+
+    let doors = std::mem::take(&mut encounter.doors);
+    for entity in doors.into_iter().chain(encounter.boss.take()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    encounter.level = None;
+}
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// A boss and its attack patterns, loaded from a `.boss.json` file.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct BossScript {
+    pub name: String,
+    /// LDtk identifier of the level the fight happens in
+    pub level: String,
+    pub health: i32,
+    pub size: [f32; 2],
+    /// Pixels from the bottom-left corner of the level
+    pub spawn: [f32; 2],
+    pub contact_damage: i32,
+    /// Stinger played when the arena locks
+    #[serde(default)]
+    pub intro: Option<String>,
+    /// Looping sound coming from the boss while it lives
+    #[serde(default)]
+    pub ambient: Option<String>,
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BossPhase {
+    pub name: String,
+    /// Phase starts once health drops to this fraction of the maximum
+    pub below_health: f32,
+    pub pattern: Vec<BossAttack>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "attack")]
+pub enum BossAttack {
+    Wait {
+        duration: f32,
+    },
+    Charge {
+        duration: f32,
+        speed: f32,
+    },
+    Leap {
+        duration: f32,
+        speed_x: f32,
+        speed_y: f32,
+    },
+}
+
+impl BossAttack {
+    pub fn duration(&self) -> f32 {
+        match self {
+            BossAttack::Wait { duration }
+            | BossAttack::Charge { duration, .. }
+            | BossAttack::Leap { duration, .. } => *duration,
+        }
+    }
+}
+
+impl BossScript {
+    /// Index of the last phase whose threshold has been crossed.
+    pub fn phase_for(&self, health_fraction: f32) -> usize {
+        self.phases
+            .iter()
+            .rposition(|phase| health_fraction <= phase.below_health)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(thresholds: &[f32]) -> BossScript {
+        BossScript {
+            name: "Test".to_string(),
+            level: "Boss_room".to_string(),
+            health: 10,
+            size: [32.0, 32.0],
+            spawn: [0.0, 0.0],
+            contact_damage: 1,
+            intro: None,
+            ambient: None,
+            phases: thresholds
+                .iter()
+                .map(|below_health| BossPhase {
+                    name: format!("below {below_health}"),
+                    below_health: *below_health,
+                    pattern: vec![BossAttack::Wait { duration: 1.0 }],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn phase_switches_at_thresholds() {
+        let script = script(&[1.0, 0.5, 0.2]);
+
+        assert_eq!(script.phase_for(1.0), 0);
+        assert_eq!(script.phase_for(0.51), 0);
+        assert_eq!(script.phase_for(0.5), 1);
+        assert_eq!(script.phase_for(0.3), 1);
+        assert_eq!(script.phase_for(0.2), 2);
+        assert_eq!(script.phase_for(0.0), 2);
+    }
+
+    #[test]
+    fn first_phase_covers_health_above_every_threshold() {
+        let script = script(&[0.8, 0.4]);
+
+        assert_eq!(script.phase_for(1.0), 0);
+        assert_eq!(script.phase_for(0.6), 0);
+        assert_eq!(script.phase_for(0.4), 1);
+    }
+
+    #[test]
+    fn boss_room_script_parses() {
+        let script: BossScript =
+            serde_json::from_str(include_str!("../../assets/bosses/boss_room.boss.json")).unwrap();
+
+        assert_eq!(script.level, "Boss_room");
+        assert_eq!(script.phase_for(1.0), 0);
+        assert_eq!(script.phase_for(0.25), script.phases.len() - 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! Wall cells (IntGrid value 3) that can be destroyed by attacks. Broken cells
+//! are remembered per level in `SaveData`. Walls inside a `SecretArea` region
+//! play the secret jingle when they break.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use rand::Rng;
+
+use crate::audio::music::{PlayStinger, SECRET_JINGLE};
+use crate::health::{Died, Health};
+use crate::navigation::{build_nav_graphs, NavGraphs};
+use crate::physics::{self, layers, BodyKind};
+use crate::registry;
+use crate::save::{SaveData, SaveRequested};
+
+const BREAKABLE_HP: i32 = 3;
+const DEBRIS_PIECES: usize = 6;
+const DEBRIS_LIFETIME: f32 = 0.6;
+const DEBRIS_GRAVITY: f32 = 600.0;
+
+pub struct BreakablePlugin;
+
+impl Plugin for BreakablePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_int_cell::<BreakableBundle>(registry::BREAKABLE);
+        app.register_ldtk_entity::<SecretAreaBundle>(registry::SECRET_AREA);
+        app.add_systems(Update, on_add_breakable.after(build_nav_graphs));
+        app.add_systems(FixedUpdate, break_walls);
+        app.add_systems(Update, update_debris);
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Breakable;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct BreakableBundle {
+    breakable: Breakable,
+}
+
+/// Collider and health of a breakable cell, spawned as a child of the tile.
+#[derive(Component, Debug)]
+pub struct BreakableWall {
+    pub tile: Entity,
+    pub level_iid: LevelIid,
+    pub coords: GridCoords,
+}
+
+/// Region of a level hiding a secret, centered on its transform.
+#[derive(Component, Clone, Debug, Default)]
+pub struct SecretArea {
+    pub half_size: Vec2,
+    pub play_jingle: bool,
+}
+
+impl SecretArea {
+    fn from_fields(entity_instance: &EntityInstance) -> Self {
+        SecretArea {
+            half_size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32) / 2.0,
+            play_jingle: entity_instance
+                .get_bool_field("playSecretJingle")
+                .copied()
+                .unwrap_or(true),
+        }
+    }
+
+    pub fn contains(&self, center: Vec2, point: Vec2) -> bool {
+        let offset = (point - center).abs();
+        offset.x <= self.half_size.x && offset.y <= self.half_size.y
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct SecretAreaBundle {
+    #[with(SecretArea::from_fields)]
+    secret_area: SecretArea,
+}
+
+#[derive(Component)]
+struct Debris {
+    velocity: Vec2,
+    remaining: f32,
+}
+
+/// Walks up from an IntGrid tile (tile -> layer -> level) to its level.
+fn level_of(
+    entity: Entity,
+    parents: &Query<&Parent>,
+    levels: &Query<&LevelIid>,
+) -> Option<LevelIid> {
+    let layer = parents.get(entity).ok()?.get();
+    let level = parents.get(layer).ok()?.get();
+    levels.get(level).ok().cloned()
+}
+
+fn on_add_breakable(
+    mut commands: Commands,
+    save: Res<SaveData>,
+    mut nav_graphs: ResMut<NavGraphs>,
+    tiles: Query<(Entity, &GridCoords), Added<Breakable>>,
+    parents: Query<&Parent>,
+    levels: Query<&LevelIid>,
+) {
+    for (entity, coords) in tiles.iter() {
+        let Some(level_iid) = level_of(entity, &parents, &levels) else {
+            continue;
+        };
+
+        if save.is_wall_broken(level_iid.as_str(), *coords) {
+            if let Some(graph) = nav_graphs.get_mut(&level_iid) {
+                graph.set_value(*coords, 0);
+            }
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        commands.entity(entity).with_child((
+            BreakableWall {
+                tile: entity,
+                level_iid,
+                coords: *coords,
+            },
+            Health::new(BREAKABLE_HP),
+            Sprite::from_color(Color::srgb(0.55, 0.35, 0.24), Vec2::splat(16.0)),
+            // IntGrid tiles are already placed at the center of their cell
+            Transform::default(),
+            physics::cuboid(8.0, 8.0),
+            physics::body(BodyKind::Fixed),
+            physics::friction(0.0),
+            physics::collision_layers(layers::WORLD, layers::ALL),
+        ));
+    }
+}
+
+fn break_walls(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    walls: Query<(&BreakableWall, &GlobalTransform)>,
+    secret_areas: Query<(&SecretArea, &GlobalTransform)>,
+    mut save: ResMut<SaveData>,
+    mut nav_graphs: ResMut<NavGraphs>,
+    mut save_requests: EventWriter<SaveRequested>,
+    mut stingers: EventWriter<PlayStinger>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for Died(entity) in died.read() {
+        let Ok((wall, transform)) = walls.get(*entity) else {
+            continue;
+        };
+
+        debug!("Broke wall at {:?} in {}", wall.coords, wall.level_iid);
+        save.break_wall(wall.level_iid.as_str(), wall.coords);
+        save_requests.send(SaveRequested);
+
+        let center = transform.translation();
+        let secret = secret_areas.iter().any(|(area, area_transform)| {
+            area.play_jingle
+                && area.contains(area_transform.translation().truncate(), center.truncate())
+        });
+        if secret {
+            stingers.send(PlayStinger(SECRET_JINGLE.to_string()));
+        }
+
+        if let Some(graph) = nav_graphs.get_mut(&wall.level_iid) {
+            graph.set_value(wall.coords, 0);
+        }
+
+        for _ in 0..DEBRIS_PIECES {
+            commands.spawn((
+                Debris {
+                    velocity: Vec2::new(rng.gen_range(-120.0..120.0), rng.gen_range(60.0..220.0)),
+                    remaining: DEBRIS_LIFETIME,
+                },
+                Sprite::from_color(Color::srgb(0.55, 0.35, 0.24), Vec2::splat(4.0)),
+                Transform::from_translation(center + Vec3::Z),
+            ));
+        }
+
+        commands.entity(wall.tile).despawn_recursive();
+    }
+}
+
+fn update_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut debris: Query<(Entity, &mut Debris, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut piece, mut transform, mut sprite) in debris.iter_mut() {
+        piece.remaining -= dt;
+        if piece.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        piece.velocity.y -= DEBRIS_GRAVITY * dt;
+        transform.translation += (piece.velocity * dt).extend(0.0);
+        sprite.color.set_alpha(piece.remaining / DEBRIS_LIFETIME);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_area_contains_points_inside_its_bounds() {
+        let area = SecretArea {
+            half_size: Vec2::new(32.0, 16.0),
+            play_jingle: true,
+        };
+        let center = Vec2::new(100.0, 50.0);
+
+        assert!(area.contains(center, center));
+        assert!(area.contains(center, Vec2::new(132.0, 66.0)));
+        assert!(!area.contains(center, Vec2::new(140.0, 50.0)));
+        assert!(!area.contains(center, Vec2::new(100.0, 30.0)));
+    }
+}
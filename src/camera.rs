@@ -1,7 +1,11 @@
 use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowResolution};
 use bevy_ecs_ldtk::prelude::*;
 
-use crate::character::Character;
+use crate::level::LevelIndex;
+
+/// Projection scale at a level's default `cameraZoom` of 1.
+pub const CAMERA_SCALE: f32 = 0.3;
 
 #[derive(Component)]
 pub struct CameraTarget;
@@ -19,7 +23,7 @@ fn setup(mut commands: Commands) {
     commands.spawn((
         Camera2d,
         OrthographicProjection {
-            scale: 0.3,
+            scale: CAMERA_SCALE,
             far: 1000.0,
             near: -1000.0,
             ..OrthographicProjection::default_2d()
@@ -30,80 +34,108 @@ fn setup(mut commands: Commands) {
 
 fn simple_camera_follow(
     time: Res<Time>,
-    mut camera: Query<(&mut Transform, &OrthographicProjection), (Without<CameraTarget>)>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), Without<CameraTarget>>,
     target: Query<&Transform, (With<CameraTarget>, Without<Camera2d>)>,
 ) {
-    let (mut camera_transform, projection) = camera.single_mut();
+    let Ok((mut camera_transform, projection)) = camera.get_single_mut() else {
+        return;
+    };
 
     if let Ok(target_transform) = target.get_single() {
         camera_transform.translation = target_transform.translation;
     }
 }
 
+/// Half the width of the world a `window_width` wide view shows at `scale`,
+/// which level `cameraZoom` fields change.
+pub fn half_view_width(window_width: f32, scale: f32) -> f32 {
+    window_width / 2.0 * scale
+}
+
+/// Camera x keeping a view `half_width` wide inside `min..max`. Levels
+/// narrower than the view are centered instead.
+pub fn clamp_to_level(x: f32, min: f32, max: f32, half_width: f32) -> f32 {
+    if max - min <= half_width * 2.0 {
+        (min + max) / 2.0
+    } else {
+        x.clamp(min + half_width, max - half_width)
+    }
+}
+
 fn camera_follow_player(
     time: Res<Time>,
-    mut camera: Query<(&mut Transform, &OrthographicProjection), (Without<CameraTarget>)>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), Without<CameraTarget>>,
     target: Query<&Transform, (With<CameraTarget>, Without<Camera2d>)>,
-    level_query: Query<
-        (&Transform, &LevelIid),
-        (Without<OrthographicProjection>, Without<Character>),
-    >,
-    ldtk_projects: Query<&LdtkProjectHandle>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    level_index: Res<LevelIndex>,
     level_selection: Res<LevelSelection>,
 ) {
-    let (mut camera_transform, projection) = camera.single_mut();
-
-    // Get current level boundaries
-    let mut level_min_x = f32::NEG_INFINITY;
-    let mut level_max_x = f32::INFINITY;
-    let px_wid = 16.0;
-
-    // Extract level boundaries from LDtk project
-    for (level_transform, level_iid) in &level_query {
-        let ldtk_project = ldtk_project_assets
-            .get(ldtk_projects.single())
-            .expect("Project should be loaded if level has spawned");
-
-        let level = ldtk_project
-            .get_raw_level_by_iid(&level_iid.to_string())
-            .expect("Spawned level should exist in LDtk project");
-
-        if level_selection.is_match(&LevelIndices::default(), level) {
-            level_min_x = level_transform.translation.x;
-            level_max_x = level_transform.translation.x + (level.px_wid as f32);
-        }
+    let Ok((mut camera_transform, projection)) = camera.get_single_mut() else {
+        return;
+    };
+    let Ok(player_transform) = target.get_single() else {
+        return;
+    };
+
+    // Headless runs have no window; they see as much as the default one would
+    let window_width = windows
+        .get_single()
+        .map_or(WindowResolution::default().width(), Window::width);
+    let half_width = half_view_width(window_width, projection.scale);
+
+    let deadzone = 10.0;
+
+    // Center the player by removing the x-offset and maintaining y position
+    let target_pos = Vec3::new(
+        player_transform.translation.x,
+        camera_transform.translation.y,
+        camera_transform.translation.z,
+    );
+
+    let distance = target_pos - camera_transform.translation;
+
+    if distance.length() > deadzone {
+        let lerp_speed = 5.0;
+        let lerp_factor = (1.0 - (-lerp_speed * time.delta_secs()).exp()).min(1.0);
+
+        camera_transform.translation = camera_transform.translation.lerp(target_pos, lerp_factor);
     }
 
-    if let Ok(player_transform) = target.get_single() {
-        let deadzone = 10.0;
-
-        // Adjust the level boundaries to account for camera view
-        let camera_min_x = level_min_x + (px_wid * 12.0);
-        let camera_max_x = level_max_x - (px_wid * 12.0);
-
-        // Center the player by removing the x-offset and maintaining y position
-        let target_pos = Vec3::new(
-            player_transform.translation.x,
-            camera_transform.translation.y,
-            camera_transform.translation.z,
+    // Without a loaded level the camera is not clamped
+    if let Some(level) = level_index.selected(&level_selection) {
+        camera_transform.translation.x = clamp_to_level(
+            camera_transform.translation.x,
+            level.rect.min.x,
+            level.rect.max.x,
+            half_width,
         );
+    }
+}
 
-        let distance = target_pos - camera_transform.translation;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if distance.length() > deadzone {
-            let lerp_speed = 5.0;
-            let lerp_factor = (1.0 - (-lerp_speed * time.delta_secs()).exp()).min(1.0);
+    #[test]
+    fn camera_stays_a_half_view_from_the_edges() {
+        assert_eq!(clamp_to_level(10.0, 0.0, 640.0, 192.0), 192.0);
+        assert_eq!(clamp_to_level(300.0, 0.0, 640.0, 192.0), 300.0);
+        assert_eq!(clamp_to_level(630.0, 0.0, 640.0, 192.0), 448.0);
+    }
 
-            // First lerp to the target
-            let new_pos = camera_transform.translation.lerp(target_pos, lerp_factor);
+    #[test]
+    fn zooming_in_narrows_the_margin() {
+        let unzoomed = half_view_width(1280.0, CAMERA_SCALE);
+        let zoomed = half_view_width(1280.0, CAMERA_SCALE / 2.0);
 
-            // Then clamp the camera position to prevent seeing outside the level
-            camera_transform.translation.x = new_pos.x.clamp(camera_min_x, camera_max_x);
-            camera_transform.translation.y = new_pos.y;
-            camera_transform.translation.z = new_pos.z;
+        assert_eq!(zoomed, unzoomed / 2.0);
+        assert_eq!(clamp_to_level(0.0, 0.0, 640.0, zoomed), zoomed);
+    }
 
-            println!("Final camera position: {}", camera_transform.translation.x);
-        }
+    #[test]
+    fn narrow_levels_are_centered() {
+        // Sewers1 and the other 256px wide levels are narrower than the view
+        assert_eq!(clamp_to_level(600.0, 512.0, 768.0, 192.0), 640.0);
+        assert_eq!(clamp_to_level(0.0, 0.0, 384.0, 192.0), 192.0);
     }
 }
@@ -0,0 +1,339 @@
+//! Animates characters from a sprite sheet. Clips, as frame ranges of the
+//! sheet with their own frame rates, are listed in an `.anim.json` file; the
+//! clip played follows what the controller is doing.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::character::controller_kinematic::{Grounded, KinematicVelocity};
+use crate::character::{Character, DoubleJumped, Facing, Landed};
+use crate::health::Hurt;
+use crate::json_asset::JsonAssetPlugin;
+use crate::physics::BodyVelocity;
+
+const PLAYER_SHEET: &str = "animations/player.anim.json";
+// Vertical speed below which a body without `Grounded` counts as standing
+const STILL_SPEED: f32 = 5.0;
+
+pub struct CharacterAnimationPlugin;
+
+impl Plugin for CharacterAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(JsonAssetPlugin::<AnimationSheet>::new(&["anim.json"]));
+        app.add_systems(Startup, load_player_sheet);
+        app.add_systems(
+            Update,
+            (
+                attach_animator,
+                track_animation_events,
+                choose_clip,
+                advance_frames,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    DoubleJump,
+    WallSlide,
+    Climb,
+    Hurt,
+}
+
+/// A range of frames, both ends included, counted row by row from the top left.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Clip {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+    /// Non-looping clips stop on their last frame
+    #[serde(default = "looping_by_default")]
+    pub looping: bool,
+}
+
+fn looping_by_default() -> bool {
+    true
+}
+
+impl Clip {
+    fn len(&self) -> usize {
+        self.last.saturating_sub(self.first) + 1
+    }
+
+    /// Sheet index shown `elapsed` seconds into the clip.
+    pub fn frame_at(&self, elapsed: f32) -> usize {
+        let frame = (elapsed * self.fps).max(0.0) as usize;
+        let frame = if self.looping {
+            frame % self.len()
+        } else {
+            frame.min(self.len() - 1)
+        };
+        self.first + frame
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.len() as f32 / self.fps
+    }
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct AnimationSheet {
+    /// Asset path of the sheet image
+    pub image: String,
+    pub frame_size: [u32; 2],
+    pub columns: u32,
+    pub rows: u32,
+    pub clips: HashMap<AnimationState, Clip>,
+}
+
+impl AnimationSheet {
+    /// The clip for `state`, or idle when the sheet has none.
+    pub fn clip(&self, state: AnimationState) -> Option<&Clip> {
+        self.clips
+            .get(&state)
+            .or_else(|| self.clips.get(&AnimationState::Idle))
+    }
+}
+
+/// Set by controllers that can climb.
+#[derive(Component, Debug)]
+pub struct Climbing;
+
+/// Set by controllers that can slide down walls.
+#[derive(Component, Debug)]
+pub struct WallSliding;
+
+#[derive(Component, Debug, Default)]
+pub struct Animator {
+    pub state: AnimationState,
+    elapsed: f32,
+    /// Seconds left of the hurt clip
+    hurt: f32,
+    double_jumping: bool,
+}
+
+#[derive(Resource)]
+struct PlayerSheet {
+    sheet: Handle<AnimationSheet>,
+    image: Option<Handle<Image>>,
+    layout: Option<Handle<TextureAtlasLayout>>,
+}
+
+fn load_player_sheet(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PlayerSheet {
+        sheet: asset_server.load(PLAYER_SHEET),
+        image: None,
+        layout: None,
+    });
+}
+
+fn attach_animator(mut commands: Commands, characters: Query<Entity, Added<Character>>) {
+    for entity in characters.iter() {
+        commands.entity(entity).insert(Animator::default());
+    }
+}
+
+fn track_animation_events(
+    sheet: Res<PlayerSheet>,
+    sheets: Res<Assets<AnimationSheet>>,
+    mut hurt: EventReader<Hurt>,
+    mut double_jumped: EventReader<DoubleJumped>,
+    mut landed: EventReader<Landed>,
+    mut animators: Query<&mut Animator>,
+) {
+    let hurt_duration = sheets
+        .get(&sheet.sheet)
+        .and_then(|sheet| sheet.clips.get(&AnimationState::Hurt))
+        .map_or(0.0, Clip::duration);
+
+    for Hurt(entity) in hurt.read() {
+        if let Ok(mut animator) = animators.get_mut(*entity) {
+            animator.hurt = hurt_duration;
+        }
+    }
+    for DoubleJumped(entity) in double_jumped.read() {
+        if let Ok(mut animator) = animators.get_mut(*entity) {
+            animator.double_jumping = true;
+        }
+    }
+    for Landed(entity) in landed.read() {
+        if let Ok(mut animator) = animators.get_mut(*entity) {
+            animator.double_jumping = false;
+        }
+    }
+}
+
+/// What the controller is doing, from most to least important.
+pub fn pick_state(
+    velocity: Vec2,
+    grounded: bool,
+    hurt: bool,
+    climbing: bool,
+    wall_sliding: bool,
+    double_jumping: bool,
+) -> AnimationState {
+    if hurt {
+        AnimationState::Hurt
+    } else if climbing {
+        AnimationState::Climb
+    } else if wall_sliding {
+        AnimationState::WallSlide
+    } else if grounded {
+        if velocity.x.abs() > STILL_SPEED {
+            AnimationState::Run
+        } else {
+            AnimationState::Idle
+        }
+    } else if double_jumping && velocity.y > 0.0 {
+        AnimationState::DoubleJump
+    } else if velocity.y > 0.0 {
+        AnimationState::Jump
+    } else {
+        AnimationState::Fall
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn choose_clip(
+    time: Res<Time>,
+    mut characters: Query<(
+        &mut Animator,
+        Option<BodyVelocity>,
+        Option<&KinematicVelocity>,
+        Has<Grounded>,
+        Has<Climbing>,
+        Has<WallSliding>,
+    )>,
+) {
+    let dt = time.delta_secs();
+
+    for (mut animator, body, kinematic, grounded, climbing, wall_sliding) in characters.iter_mut() {
+        let velocity = kinematic
+            .map(|velocity| velocity.0)
+            .or_else(|| body.map(|body| body.linear()))
+            .unwrap_or_default();
+        // Dynamic bodies have no `Grounded`; standing still vertically is close enough
+        let grounded = grounded || (kinematic.is_none() && velocity.y.abs() < STILL_SPEED);
+        if grounded {
+            animator.double_jumping = false;
+        }
+
+        animator.hurt = (animator.hurt - dt).max(0.0);
+        let state = pick_state(
+            velocity,
+            grounded,
+            animator.hurt > 0.0,
+            climbing,
+            wall_sliding,
+            animator.double_jumping,
+        );
+
+        if state != animator.state {
+            animator.state = state;
+            animator.elapsed = 0.0;
+        } else {
+            animator.elapsed += dt;
+        }
+    }
+}
+
+fn advance_frames(
+    asset_server: Res<AssetServer>,
+    mut player_sheet: ResMut<PlayerSheet>,
+    sheets: Res<Assets<AnimationSheet>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut characters: Query<(&Animator, &Facing, &mut Sprite)>,
+) {
+    let Some(sheet) = sheets.get(&player_sheet.sheet) else {
+        return;
+    };
+
+    if player_sheet.layout.is_none() {
+        player_sheet.image = Some(asset_server.load(sheet.image.clone()));
+        player_sheet.layout = Some(layouts.add(TextureAtlasLayout::from_grid(
+            UVec2::from(sheet.frame_size),
+            sheet.columns,
+            sheet.rows,
+            None,
+            None,
+        )));
+    }
+    let (Some(image), Some(layout)) = (&player_sheet.image, &player_sheet.layout) else {
+        return;
+    };
+
+    for (animator, facing, mut sprite) in characters.iter_mut() {
+        let Some(clip) = sheet.clip(animator.state) else {
+            continue;
+        };
+        let index = clip.frame_at(animator.elapsed);
+
+        if sprite.image != *image {
+            sprite.image = image.clone();
+        }
+        match &mut sprite.texture_atlas {
+            Some(atlas) if atlas.layout == *layout => {
+                if atlas.index != index {
+                    atlas.index = index;
+                }
+            }
+            atlas => {
+                *atlas = Some(TextureAtlas {
+                    layout: layout.clone(),
+                    index,
+                });
+            }
+        }
+
+        let flip = facing.0 < 0.0;
+        if sprite.flip_x != flip {
+            sprite.flip_x = flip;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clips_loop_or_hold_their_last_frame() {
+        let run = Clip {
+            first: 6,
+            last: 11,
+            fps: 10.0,
+            looping: true,
+        };
+        assert_eq!(run.frame_at(0.0), 6);
+        assert_eq!(run.frame_at(0.55), 11);
+        assert_eq!(run.frame_at(0.65), 6);
+
+        let hurt = Clip {
+            looping: false,
+            ..run
+        };
+        assert_eq!(hurt.frame_at(5.0), 11);
+    }
+
+    #[test]
+    fn hurt_wins_over_movement() {
+        let state = pick_state(Vec2::new(100.0, 200.0), false, true, false, false, true);
+        assert_eq!(state, AnimationState::Hurt);
+
+        let state = pick_state(Vec2::new(0.0, 200.0), false, false, false, false, true);
+        assert_eq!(state, AnimationState::DoubleJump);
+
+        let state = pick_state(Vec2::new(0.0, -50.0), false, false, false, false, false);
+        assert_eq!(state, AnimationState::Fall);
+    }
+}
@@ -1,4 +1,27 @@
 use bevy::prelude::*;
 
 #[derive(Component, Default)]
+#[require(Facing)]
 pub struct Character;
+
+/// Horizontal direction the character looks at: -1.0 for left, 1.0 for right.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Facing(pub f32);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Facing(1.0)
+    }
+}
+
+/// The character left the ground with a jump.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Jumped(pub Entity);
+
+/// A second jump started in mid-air.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DoubleJumped(pub Entity);
+
+/// The character touched the ground after being in the air.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Landed(pub Entity);
@@ -1,9 +1,21 @@
+use crate::camera::CameraTarget;
+use crate::character::input::PlayerActions;
+use crate::character::{Character, DoubleJumped, Jumped, Landed};
+use crate::physics::{self, layers, BodyKind, KinematicMover, KinematicMoverOutput};
+use bevy::app::RunFixedMainLoopSystem;
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
 
-const GRAVITY: f32 = 98.1;
-const JUMP_FORCE: f32 = 4_000.0;
+const GRAVITY: f32 = 980.0;
+const MAX_FALL_SPEED: f32 = 600.0;
+const JUMP_SPEED: f32 = 320.0;
 const MOVE_SPEED: f32 = 200.0;
+const AIR_CONTROL: f32 = 0.8;
+// Fraction of a knockback impulse left after one second
+const IMPULSE_RETAINED_PER_SECOND: f32 = 0.02;
+const IMPULSE_REST: f32 = 1.0;
+
+/// Middle of the character capsule, relative to the entity.
+pub const CAPSULE_CENTER: Vec2 = Vec2::new(0.0, -4.0);
 
 #[derive(Component)]
 pub struct Grounded;
@@ -11,19 +23,62 @@ pub struct Grounded;
 #[derive(Component)]
 pub struct DoubleJump;
 
+/// Velocity owned by the kinematic controller, integrated once per fixed tick.
+#[derive(Component, Default, Debug)]
+pub struct KinematicVelocity(pub Vec2);
+
+/// Push from outside the controller, such as knockback. The vertical part
+/// launches the body once; the horizontal part adds to the player's own
+/// movement and fades out instead of being overwritten by it.
+#[derive(Component, Default, Debug)]
+pub struct KinematicImpulse(pub Vec2);
+
+/// Multiplies the controller gravity; kinematic bodies ignore the engine's.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GravityScale(pub f32);
+
+impl Default for GravityScale {
+    fn default() -> Self {
+        GravityScale(1.0)
+    }
+}
+
+/// Positions at the last two fixed ticks, used to smooth rendering between them.
+#[derive(Component, Default)]
+pub struct RenderInterpolation {
+    previous: Option<Vec2>,
+    current: Option<Vec2>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Jump {
+    Single,
+    Double,
+}
+
 pub struct KinematicControllerPlugin;
 
 impl Plugin for KinematicControllerPlugin {
     fn build(&self, app: &mut App) {
+        app.register_required_components::<KinematicMover, KinematicVelocity>();
+        app.register_required_components::<KinematicMover, KinematicImpulse>();
+        app.register_required_components::<KinematicMover, RenderInterpolation>();
+        app.register_required_components::<KinematicMover, GravityScale>();
+        app.add_event::<Jumped>();
+        app.add_event::<DoubleJumped>();
+        app.add_event::<Landed>();
+
+        app.add_systems(
+            FixedUpdate,
+            (remove_grounded, add_grounded, integrate_velocity).chain(),
+        );
+        app.add_systems(FixedLast, record_physical_translation);
+        app.add_systems(Update, on_add_character);
         app.add_systems(
-            Update,
+            RunFixedMainLoop,
             (
-                remove_grounded,
-                add_grounded,
-                jump,
-                player_movement,
-                gravity,
-                read_kineamtic_controller,
+                restore_physical_translation.in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+                interpolate_rendered_translation.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
             ),
         );
     }
@@ -31,129 +86,332 @@ impl Plugin for KinematicControllerPlugin {
 
 fn add_grounded(
     mut commands: Commands,
-    query: Query<(Entity, &KinematicCharacterControllerOutput), Without<Grounded>>,
+    query: Query<(Entity, &KinematicMoverOutput), Without<Grounded>>,
+    mut landed: EventWriter<Landed>,
 ) {
     if let Ok((entity, controller)) = query.get_single() {
-        if controller.grounded || controller.effective_translation.y.abs() < 0.01 {
+        if controller.grounded {
             commands.entity(entity).insert(Grounded);
             commands.entity(entity).remove::<DoubleJump>();
-            println!("Grounded");
+            landed.send(Landed(entity));
         }
     }
 }
 
 fn remove_grounded(
     mut commands: Commands,
-    query: Query<(Entity, &KinematicCharacterControllerOutput), With<Grounded>>,
+    query: Query<(Entity, &KinematicMoverOutput), With<Grounded>>,
 ) {
     if let Ok((entity, controller)) = query.get_single() {
-        if !controller.grounded && controller.effective_translation.y.abs() > 0.01 {
+        if !controller.grounded {
             commands.entity(entity).remove::<Grounded>();
-            println!("On Air");
         }
     }
 }
 
-fn gravity(time: Res<Time>, mut query: Query<&mut KinematicCharacterController>) {
-    for mut controller in query.iter_mut() {
-        let gravity_vector = Vec2::new(0.0, -GRAVITY * time.delta_secs());
+/// Advances the controller velocity by one tick of `dt` seconds.
+pub fn step_velocity(
+    velocity: Vec2,
+    actions: &PlayerActions,
+    grounded: bool,
+    double_jumped: bool,
+    gravity_scale: f32,
+    dt: f32,
+) -> (Vec2, Option<Jump>) {
+    let mut velocity = velocity;
+    let mut jump = None;
 
-        controller.translation = match controller.translation {
-            Some(translation) => Some(translation + gravity_vector),
-            None => Some(gravity_vector),
-        };
+    // Reduce horizontal speed when in air
+    let control = if grounded { 1.0 } else { AIR_CONTROL };
+    velocity.x = actions.horizontal() * MOVE_SPEED * control;
+
+    if grounded && velocity.y < 0.0 {
+        velocity.y = 0.0;
+    }
+
+    if actions.jump && (grounded || !double_jumped) {
+        velocity.y = JUMP_SPEED;
+        jump = Some(if grounded { Jump::Single } else { Jump::Double });
     }
+
+    velocity.y = (velocity.y - GRAVITY * gravity_scale * dt).max(-MAX_FALL_SPEED);
+
+    (velocity, jump)
 }
 
-fn read_kineamtic_controller(controllers: Query<(Entity, &KinematicCharacterControllerOutput)>) {
-    // for (entity, output) in controllers.iter() {
-    //     println!(
-    //         "Entity {:?} moved by {:?} and touches the ground: {:?}",
-    //         entity, output.effective_translation, output.grounded
-    //     );
-    // }
+/// Adds an external impulse on top of a controller velocity for one tick of
+/// `dt` seconds. Returns the velocity and what is left of the impulse.
+pub fn apply_impulse(velocity: Vec2, impulse: Vec2, dt: f32) -> (Vec2, Vec2) {
+    let mut velocity = velocity;
+    velocity.x += impulse.x;
+    if impulse.y != 0.0 {
+        velocity.y = impulse.y;
+    }
+
+    let mut remaining = impulse.x * IMPULSE_RETAINED_PER_SECOND.powf(dt);
+    if remaining.abs() < IMPULSE_REST {
+        remaining = 0.0;
+    }
+
+    (velocity, Vec2::new(remaining, 0.0))
 }
 
-fn jump(
+pub(crate) fn integrate_velocity(
     mut commands: Commands,
     time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut controllers: Query<
-        (Entity, &mut KinematicCharacterController, Option<&Grounded>),
-        Without<DoubleJump>,
-    >,
+    actions: Res<PlayerActions>,
+    mut controllers: Query<(
+        Entity,
+        &mut KinematicVelocity,
+        &mut KinematicImpulse,
+        &mut KinematicMover,
+        &GravityScale,
+        Has<Grounded>,
+        Has<DoubleJump>,
+    )>,
+    mut jumped: EventWriter<Jumped>,
+    mut double_jumped: EventWriter<DoubleJumped>,
 ) {
-    let mut direction = Vec2::ZERO;
+    let dt = time.delta_secs();
 
-    if keys.just_pressed(KeyCode::KeyW)
-        || keys.just_pressed(KeyCode::ArrowUp)
-        || keys.just_pressed(KeyCode::Space)
+    for (
+        entity,
+        mut velocity,
+        mut impulse,
+        mut controller,
+        gravity_scale,
+        grounded,
+        double_jumped,
+    ) in controllers.iter_mut()
     {
-        direction += Vec2::new(0.0, 1.0);
-    }
+        let (next, jump) = step_velocity(
+            velocity.0,
+            &actions,
+            grounded,
+            double_jumped,
+            gravity_scale.0,
+            dt,
+        );
 
-    if direction.length() == 0.0 {
-        return;
+        match jump {
+            Some(Jump::Single) => {
+                jumped.send(Jumped(entity));
+            }
+            Some(Jump::Double) => {
+                commands.entity(entity).insert(DoubleJump);
+                double_jumped.send(DoubleJumped(entity));
+            }
+            None => {}
+        }
+
+        let (next, remaining) = apply_impulse(next, impulse.0, dt);
+        impulse.0 = remaining;
+
+        velocity.0 = next;
+        controller.translation = Some(next * dt);
     }
+}
 
-    // Check is moving forward or backward
-    if keys.pressed(KeyCode::KeyD) {
-        direction += Vec2::new(1.0, 0.0);
+fn on_add_character(mut commands: Commands, query: Query<Entity, Added<Character>>) {
+    if let Ok(entity) = query.get_single() {
+        commands.entity(entity).insert((
+            physics::capsule(
+                CAPSULE_CENTER - Vec2::new(0.0, 4.0),
+                CAPSULE_CENTER + Vec2::new(0.0, 4.0),
+                8.0,
+            ),
+            physics::body(BodyKind::Kinematic),
+            KinematicMover::default(),
+            CameraTarget,
+            physics::report_collisions(),
+            physics::collision_layers(layers::PLAYER, layers::ALL),
+        ));
     }
+}
 
-    if keys.pressed(KeyCode::KeyA) {
-        direction += Vec2::new(-1.0, 0.0);
+fn record_physical_translation(mut query: Query<(&Transform, &mut RenderInterpolation)>) {
+    for (transform, mut interpolation) in query.iter_mut() {
+        let translation = transform.translation.xy();
+        interpolation.previous = interpolation.current.or(Some(translation));
+        interpolation.current = Some(translation);
     }
+}
 
-    // Normalize the direction
-    direction = direction.normalize() * JUMP_FORCE * time.delta_secs();
+/// Puts the simulated position back before the fixed steps run, so physics
+/// never sees the interpolated one.
+fn restore_physical_translation(mut query: Query<(&mut Transform, &RenderInterpolation)>) {
+    for (mut transform, interpolation) in query.iter_mut() {
+        if let Some(current) = interpolation.current {
+            transform.translation.x = current.x;
+            transform.translation.y = current.y;
+        }
+    }
+}
 
-    for (entity, mut controller, grounded) in controllers.iter_mut() {
-        let jump_vector = direction;
-        controller.translation = match controller.translation {
-            Some(translation) => Some(translation + jump_vector),
-            None => Some(jump_vector),
-        };
+fn interpolate_rendered_translation(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &RenderInterpolation)>,
+) {
+    let alpha = fixed_time.overstep_fraction();
 
-        if grounded.is_none() {
-            commands.entity(entity).insert(DoubleJump);
-            println!("Double Jump");
-        } else {
-            println!("Single Jump");
+    for (mut transform, interpolation) in query.iter_mut() {
+        if let (Some(previous), Some(current)) = (interpolation.previous, interpolation.current) {
+            let rendered = previous.lerp(current, alpha);
+            transform.translation.x = rendered.x;
+            transform.translation.y = rendered.y;
         }
     }
 }
 
-fn player_movement(
-    time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut controllers: Query<(&mut KinematicCharacterController, Option<&Grounded>)>,
-) {
-    let mut direction = Vec2::ZERO;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::input::{snapshot_player_actions, PlayerInputPlugin};
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
 
-    if keys.pressed(KeyCode::KeyA) {
-        direction.x -= MOVE_SPEED;
+    const TICKS: usize = 120;
+
+    #[derive(Resource, Default)]
+    struct Trajectory(Vec<Vec2>);
+
+    #[derive(Resource, Default)]
+    struct Tick(usize);
+
+    // Hold right the whole time, jump on tick 5 and double jump on tick 30
+    fn scripted_actions(mut tick: ResMut<Tick>, mut actions: ResMut<PlayerActions>) {
+        *actions = PlayerActions {
+            left: false,
+            right: true,
+            jump: tick.0 == 5 || tick.0 == 30,
+            ..default()
+        };
+        tick.0 += 1;
     }
-    if keys.pressed(KeyCode::KeyD) {
-        direction.x += MOVE_SPEED;
+
+    // Stand-in for the physics backend: free movement above a floor at y = 0
+    fn move_above_floor(
+        mut commands: Commands,
+        mut query: Query<(Entity, &mut KinematicMover, &mut Transform)>,
+    ) {
+        for (entity, mut mover, mut transform) in query.iter_mut() {
+            let start = transform.translation.xy();
+            let mut end = start + mover.translation.take().unwrap_or_default();
+            let grounded = end.y <= 0.0;
+            end.y = end.y.max(0.0);
+
+            transform.translation.x = end.x;
+            transform.translation.y = end.y;
+            commands.entity(entity).insert(KinematicMoverOutput {
+                grounded,
+                effective_translation: end - start,
+            });
+        }
     }
 
-    if direction.length() == 0.0 {
-        return;
+    fn record(query: Query<&RenderInterpolation>, mut trajectory: ResMut<Trajectory>) {
+        for interpolation in query.iter() {
+            trajectory.0.extend(interpolation.current);
+        }
     }
 
-    direction = direction * time.delta_secs();
+    fn simulate(render_hz: f64) -> Vec<Vec2> {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<ButtonInput<KeyCode>>()
+            .add_plugins(PlayerInputPlugin)
+            .add_plugins(KinematicControllerPlugin)
+            .init_resource::<Trajectory>()
+            .init_resource::<Tick>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / render_hz,
+            )))
+            .add_systems(
+                FixedPreUpdate,
+                scripted_actions.after(snapshot_player_actions),
+            )
+            .add_systems(FixedPostUpdate, move_above_floor)
+            .add_systems(FixedLast, record.after(record_physical_translation));
+
+        app.world_mut()
+            .spawn((Transform::default(), KinematicMover::default()));
 
-    for (mut controller, grounded) in controllers.iter_mut() {
-        // Reduce horizontal speed when in air
-        if grounded.is_none() {
-            direction.x *= 0.8;
+        while app.world().resource::<Trajectory>().0.len() < TICKS {
+            app.update();
         }
 
-        // Changed to preserve any existing translation (like jump force)
-        controller.translation = match controller.translation {
-            Some(translation) => Some(direction + translation),
-            None => Some(direction),
+        app.world().resource::<Trajectory>().0[..TICKS].to_vec()
+    }
+
+    #[test]
+    fn trajectory_is_independent_of_render_rate() {
+        let reference = simulate(60.0);
+
+        assert!(reference.iter().any(|position| position.y > 0.0));
+        assert_eq!(simulate(30.0), reference);
+        assert_eq!(simulate(144.0), reference);
+    }
+
+    #[test]
+    fn falls_accelerate() {
+        let dt = 1.0 / 64.0;
+        let actions = PlayerActions::default();
+
+        let (first, _) = step_velocity(Vec2::ZERO, &actions, false, false, 1.0, dt);
+        let (second, _) = step_velocity(first, &actions, false, false, 1.0, dt);
+
+        assert!(second.y < first.y);
+        assert!(first.y < 0.0);
+    }
+
+    #[test]
+    fn only_one_jump_in_the_air() {
+        let dt = 1.0 / 64.0;
+        let jump = PlayerActions {
+            jump: true,
+            ..default()
         };
+
+        let (_, from_ground) = step_velocity(Vec2::ZERO, &jump, true, false, 1.0, dt);
+        let (_, in_air) = step_velocity(Vec2::ZERO, &jump, false, false, 1.0, dt);
+        let (_, after_double) = step_velocity(Vec2::ZERO, &jump, false, true, 1.0, dt);
+
+        assert_eq!(from_ground, Some(Jump::Single));
+        assert_eq!(in_air, Some(Jump::Double));
+        assert_eq!(after_double, None);
+    }
+
+    #[test]
+    fn knockback_fades_out_under_held_input() {
+        let dt = 1.0 / 64.0;
+        let right = PlayerActions {
+            right: true,
+            ..default()
+        };
+
+        let mut velocity = Vec2::ZERO;
+        let mut impulse = Vec2::new(-300.0, 100.0);
+        let mut pushed = Vec::new();
+
+        for _ in 0..256 {
+            let (next, _) = step_velocity(velocity, &right, true, false, 1.0, dt);
+            (velocity, impulse) = apply_impulse(next, impulse, dt);
+            pushed.push(velocity.x);
+        }
+
+        // Knocked back against the input, then recovering towards full speed
+        assert!(pushed[0] < 0.0);
+        assert!(pushed.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert_eq!(*pushed.last().unwrap(), MOVE_SPEED);
+        assert_eq!(impulse, Vec2::ZERO);
+    }
+
+    #[test]
+    fn knockback_launches_vertically_once() {
+        let (velocity, remaining) =
+            apply_impulse(Vec2::new(0.0, -50.0), Vec2::new(0.0, 100.0), 0.1);
+
+        assert_eq!(velocity.y, 100.0);
+        assert_eq!(remaining.y, 0.0);
     }
 }
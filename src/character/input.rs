@@ -0,0 +1,94 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+use crate::character::{Character, Facing};
+
+pub struct PlayerInputPlugin;
+
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerInput>();
+        app.init_resource::<PlayerActions>();
+        app.add_systems(PreUpdate, collect_player_input.after(InputSystem));
+        app.add_systems(FixedPreUpdate, snapshot_player_actions);
+        app.add_systems(FixedUpdate, update_facing);
+    }
+}
+
+/// Keyboard state gathered every frame. Presses are latched until the next
+/// fixed tick consumes them, so a tap between two ticks is never lost.
+#[derive(Resource, Default)]
+pub struct PlayerInput {
+    left: bool,
+    right: bool,
+    jump: bool,
+    attack: bool,
+}
+
+/// Actions seen by the controllers during a single fixed tick.
+#[derive(Resource, Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PlayerActions {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub attack: bool,
+}
+
+impl PlayerActions {
+    pub fn horizontal(&self) -> f32 {
+        let mut direction = 0.0;
+
+        if self.left {
+            direction -= 1.0;
+        }
+        if self.right {
+            direction += 1.0;
+        }
+
+        direction
+    }
+}
+
+fn collect_player_input(keys: Res<ButtonInput<KeyCode>>, mut input: ResMut<PlayerInput>) {
+    input.left = keys.pressed(KeyCode::KeyA) || keys.pressed(KeyCode::ArrowLeft);
+    input.right = keys.pressed(KeyCode::KeyD) || keys.pressed(KeyCode::ArrowRight);
+
+    if keys.just_pressed(KeyCode::KeyW)
+        || keys.just_pressed(KeyCode::ArrowUp)
+        || keys.just_pressed(KeyCode::Space)
+    {
+        input.jump = true;
+    }
+
+    if keys.just_pressed(KeyCode::KeyJ) || keys.just_pressed(KeyCode::KeyX) {
+        input.attack = true;
+    }
+}
+
+pub(crate) fn snapshot_player_actions(
+    mut input: ResMut<PlayerInput>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    *actions = PlayerActions {
+        left: input.left,
+        right: input.right,
+        jump: input.jump,
+        attack: input.attack,
+    };
+
+    input.jump = false;
+    input.attack = false;
+}
+
+pub(crate) fn update_facing(
+    actions: Res<PlayerActions>,
+    mut query: Query<&mut Facing, With<Character>>,
+) {
+    let direction = actions.horizontal();
+
+    if direction != 0.0 {
+        for mut facing in query.iter_mut() {
+            facing.0 = direction;
+        }
+    }
+}
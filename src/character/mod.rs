@@ -1,5 +1,6 @@
+pub mod animation;
 pub mod character;
 pub mod controller_kinematic;
-pub mod controller_velocity;
+pub mod input;
 
-pub use character::Character;
+pub use character::{Character, DoubleJumped, Facing, Jumped, Landed};
@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use super::HitStop;
+use crate::character::input::{self, PlayerActions};
+use crate::character::{Character, Facing};
+use crate::health::{DamageEvent, Health};
+use crate::physics::{self, layers, CollisionStarted};
+
+// Time after a swing ends during which the next press continues the combo
+const COMBO_WINDOW: f32 = 0.35;
+const HIT_STOP_TICKS: u32 = 4;
+
+/// One swing of the combo chain.
+struct ComboStep {
+    damage: i32,
+    knockback: Vec2,
+    /// Hitbox half extents and distance from the attacker's center
+    half_size: Vec2,
+    reach: f32,
+    duration: f32,
+}
+
+const COMBO: [ComboStep; 3] = [
+    ComboStep {
+        damage: 1,
+        knockback: Vec2::new(120.0, 60.0),
+        half_size: Vec2::new(10.0, 8.0),
+        reach: 14.0,
+        duration: 0.15,
+    },
+    ComboStep {
+        damage: 1,
+        knockback: Vec2::new(150.0, 60.0),
+        half_size: Vec2::new(10.0, 8.0),
+        reach: 14.0,
+        duration: 0.15,
+    },
+    ComboStep {
+        damage: 2,
+        knockback: Vec2::new(260.0, 160.0),
+        half_size: Vec2::new(14.0, 10.0),
+        reach: 18.0,
+        duration: 0.25,
+    },
+];
+
+pub struct MeleePlugin;
+
+impl Plugin for MeleePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_required_components::<Character, MeleeAttacker>();
+        app.add_systems(
+            FixedUpdate,
+            (start_attack, detect_hits, expire_hitboxes)
+                .chain()
+                .after(input::update_facing),
+        );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct MeleeAttacker {
+    /// Index into the combo of the last swing
+    pub combo: usize,
+    /// Seconds since the last swing started
+    since_swing: f32,
+    swinging: bool,
+}
+
+impl Default for MeleeAttacker {
+    fn default() -> Self {
+        // Never swung, so the first press always opens the combo
+        Self {
+            combo: 0,
+            since_swing: f32::INFINITY,
+            swinging: false,
+        }
+    }
+}
+
+impl MeleeAttacker {
+    /// Starts a swing, continuing the combo if the last one ended recently.
+    fn begin_swing(&mut self) -> &'static ComboStep {
+        let in_window = self.since_swing < COMBO[self.combo].duration + COMBO_WINDOW;
+        self.combo = if in_window {
+            (self.combo + 1) % COMBO.len()
+        } else {
+            0
+        };
+        self.since_swing = 0.0;
+        self.swinging = true;
+        &COMBO[self.combo]
+    }
+}
+
+/// Short-lived sensor in front of an attacker.
+#[derive(Component)]
+pub struct Hitbox {
+    pub owner: Entity,
+    pub damage: i32,
+    pub knockback: Vec2,
+    remaining: f32,
+    already_hit: HashSet<Entity>,
+}
+
+fn start_attack(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: Res<PlayerActions>,
+    mut attackers: Query<(Entity, &Facing, &mut MeleeAttacker), Without<HitStop>>,
+) {
+    for (entity, facing, mut attacker) in attackers.iter_mut() {
+        attacker.since_swing += time.delta_secs();
+
+        let duration = COMBO[attacker.combo].duration;
+        if attacker.swinging && attacker.since_swing < duration {
+            continue;
+        }
+        attacker.swinging = false;
+
+        if !actions.attack {
+            continue;
+        }
+
+        let step = attacker.begin_swing();
+        debug!("Attack {}", attacker.combo + 1);
+
+        let hitbox = commands
+            .spawn((
+                Hitbox {
+                    owner: entity,
+                    damage: step.damage,
+                    knockback: Vec2::new(step.knockback.x * facing.0, step.knockback.y),
+                    remaining: step.duration,
+                    already_hit: HashSet::new(),
+                },
+                Transform::from_xyz(step.reach * facing.0, 0.0, 0.0),
+                physics::cuboid(step.half_size.x, step.half_size.y),
+                physics::sensor(),
+                physics::report_collisions(),
+                physics::collision_layers(layers::PLAYER, layers::WORLD | layers::ENEMY),
+            ))
+            .id();
+        commands.entity(entity).add_child(hitbox);
+    }
+}
+
+fn detect_hits(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionStarted>,
+    mut hitboxes: Query<&mut Hitbox>,
+    targets: Query<(), With<Health>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for CollisionStarted(lhs, rhs) in collisions.read() {
+        for (hitbox_entity, target) in [(*lhs, *rhs), (*rhs, *lhs)] {
+            let Ok(mut hitbox) = hitboxes.get_mut(hitbox_entity) else {
+                continue;
+            };
+
+            if target == hitbox.owner || !targets.contains(target) {
+                continue;
+            }
+
+            if !hitbox.already_hit.insert(target) {
+                continue;
+            }
+
+            damage.send(DamageEvent {
+                target,
+                amount: hitbox.damage,
+                knockback: hitbox.knockback,
+            });
+            commands
+                .entity(hitbox.owner)
+                .insert(HitStop(HIT_STOP_TICKS));
+        }
+    }
+}
+
+fn expire_hitboxes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut hitboxes: Query<(Entity, &mut Hitbox)>,
+) {
+    for (entity, mut hitbox) in hitboxes.iter_mut() {
+        hitbox.remaining -= time.delta_secs();
+        if hitbox.remaining <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_swing_opens_the_combo() {
+        let mut attacker = MeleeAttacker::default();
+
+        attacker.begin_swing();
+
+        assert_eq!(attacker.combo, 0);
+    }
+
+    #[test]
+    fn swings_inside_the_window_chain() {
+        let mut attacker = MeleeAttacker::default();
+        attacker.begin_swing();
+
+        for expected in [1, 2, 0] {
+            attacker.since_swing = COMBO[attacker.combo].duration;
+            attacker.begin_swing();
+            assert_eq!(attacker.combo, expected);
+        }
+
+        attacker.since_swing = COMBO[attacker.combo].duration + COMBO_WINDOW;
+        attacker.begin_swing();
+        assert_eq!(attacker.combo, 0);
+    }
+}
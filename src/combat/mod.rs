@@ -0,0 +1,43 @@
+pub mod melee;
+pub mod projectile;
+
+use bevy::prelude::*;
+
+use crate::character::controller_kinematic::integrate_velocity;
+use crate::physics::{BodyVelocity, KinematicMover};
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(melee::MeleePlugin);
+        app.add_plugins(projectile::ProjectilePlugin);
+        app.add_systems(FixedPostUpdate, tick_hit_stop);
+        app.add_systems(FixedUpdate, freeze_hit_stopped.after(integrate_velocity));
+    }
+}
+
+/// Freezes an entity in place for a number of fixed ticks after it lands a hit.
+#[derive(Component, Debug)]
+pub struct HitStop(pub u32);
+
+fn freeze_hit_stopped(
+    mut bodies: Query<BodyVelocity, With<HitStop>>,
+    mut movers: Query<&mut KinematicMover, With<HitStop>>,
+) {
+    for mut velocity in bodies.iter_mut() {
+        velocity.set_linear(Vec2::ZERO);
+    }
+    for mut mover in movers.iter_mut() {
+        mover.translation = None;
+    }
+}
+
+fn tick_hit_stop(mut commands: Commands, mut query: Query<(Entity, &mut HitStop)>) {
+    for (entity, mut hit_stop) in query.iter_mut() {
+        hit_stop.0 = hit_stop.0.saturating_sub(1);
+        if hit_stop.0 == 0 {
+            commands.entity(entity).remove::<HitStop>();
+        }
+    }
+}
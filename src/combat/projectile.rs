@@ -0,0 +1,396 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::health::{DamageEvent, Health};
+use crate::physics::{self, layers, BodyKind, ColliderDisabled, CollisionStarted};
+
+// Entities created up front so the first volleys don't spawn anything
+const PREWARMED: usize = 16;
+const PARKED_AT: Vec3 = Vec3::new(0.0, -10_000.0, 0.0);
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FireProjectile>();
+        app.add_event::<ProjectileImpact>();
+        app.init_resource::<ProjectilePool>();
+        app.add_systems(Startup, prewarm_pool);
+        app.add_systems(
+            FixedUpdate,
+            (fire_projectiles, move_projectiles, detect_impacts).chain(),
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Team {
+    Player,
+    Enemy,
+}
+
+impl Team {
+    /// Projectiles only touch the world and the other team.
+    fn collision_layers(self) -> (u32, u32) {
+        match self {
+            Team::Player => (layers::PLAYER_PROJECTILE, layers::WORLD | layers::ENEMY),
+            Team::Enemy => (layers::ENEMY_PROJECTILE, layers::WORLD | layers::PLAYER),
+        }
+    }
+}
+
+/// How a projectile flies and what it does on impact. Speeds are in pixels
+/// per second, gravity in pixels per second squared.
+#[derive(Copy, Clone, Debug)]
+pub struct ProjectileSpec {
+    pub speed: f32,
+    pub gravity: f32,
+    /// Seconds before the projectile is returned to the pool
+    pub lifetime: f32,
+    /// Number of targets it passes through before stopping at the next one
+    pub pierce: u32,
+    pub damage: i32,
+    pub knockback: f32,
+    pub radius: f32,
+    pub color: Color,
+}
+
+impl ProjectileSpec {
+    pub const ENEMY_BOLT: ProjectileSpec = ProjectileSpec {
+        speed: 180.0,
+        gravity: 0.0,
+        lifetime: 2.0,
+        pierce: 0,
+        damage: 1,
+        knockback: 100.0,
+        radius: 3.0,
+        color: Color::srgb(0.9, 0.4, 0.1),
+    };
+}
+
+#[derive(Event, Copy, Clone, Debug)]
+pub struct FireProjectile {
+    pub owner: Entity,
+    pub team: Team,
+    pub origin: Vec2,
+    pub direction: Vec2,
+    pub spec: ProjectileSpec,
+}
+
+/// Sent whenever a projectile touches a target or the world. `target` is
+/// `None` for the world.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct ProjectileImpact {
+    pub projectile: Entity,
+    pub owner: Entity,
+    pub target: Option<Entity>,
+    pub position: Vec2,
+}
+
+#[derive(Component, Debug)]
+pub struct Projectile {
+    pub owner: Entity,
+    pub team: Team,
+    pub spec: ProjectileSpec,
+    pub velocity: Vec2,
+    age: f32,
+    pierced: u32,
+    already_hit: HashSet<Entity>,
+    active: bool,
+}
+
+/// Parked projectile entities waiting to be fired again.
+#[derive(Resource, Default)]
+pub struct ProjectilePool {
+    free: Vec<Entity>,
+}
+
+impl ProjectilePool {
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
+
+fn parked() -> impl Bundle {
+    (
+        Transform::from_translation(PARKED_AT),
+        Visibility::Hidden,
+        ColliderDisabled,
+    )
+}
+
+fn spawn_pooled(commands: &mut Commands) -> Entity {
+    commands
+        .spawn((
+            Name::new("Projectile"),
+            Sprite::default(),
+            physics::ball(1.0),
+            physics::sensor(),
+            physics::body(BodyKind::Kinematic),
+            physics::report_collisions(),
+            parked(),
+        ))
+        .id()
+}
+
+fn prewarm_pool(mut commands: Commands, mut pool: ResMut<ProjectilePool>) {
+    for _ in 0..PREWARMED {
+        let entity = spawn_pooled(&mut commands);
+        pool.free.push(entity);
+    }
+}
+
+fn release(
+    commands: &mut Commands,
+    pool: &mut ProjectilePool,
+    entity: Entity,
+    projectile: &mut Projectile,
+) {
+    if projectile.active {
+        projectile.active = false;
+        commands.entity(entity).insert(parked());
+        pool.free.push(entity);
+    }
+}
+
+fn fire_projectiles(
+    mut commands: Commands,
+    mut events: EventReader<FireProjectile>,
+    mut pool: ResMut<ProjectilePool>,
+) {
+    for event in events.read() {
+        let entity = match pool.free.pop() {
+            Some(entity) => entity,
+            None => spawn_pooled(&mut commands),
+        };
+
+        let spec = event.spec;
+        let (memberships, filters) = event.team.collision_layers();
+        let direction = event.direction.normalize_or(Vec2::X);
+
+        commands
+            .entity(entity)
+            .remove::<ColliderDisabled>()
+            .insert((
+                Projectile {
+                    owner: event.owner,
+                    team: event.team,
+                    spec,
+                    velocity: direction * spec.speed,
+                    age: 0.0,
+                    pierced: 0,
+                    already_hit: HashSet::new(),
+                    active: true,
+                },
+                Sprite::from_color(spec.color, Vec2::splat(spec.radius * 2.0)),
+                Transform::from_xyz(event.origin.x, event.origin.y, 10.0),
+                Visibility::Visible,
+                physics::ball(spec.radius),
+                physics::collision_layers(memberships, filters),
+            ));
+    }
+}
+
+fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pool: ResMut<ProjectilePool>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        if !projectile.active {
+            continue;
+        }
+
+        projectile.age += dt;
+        if projectile.age >= projectile.spec.lifetime {
+            release(&mut commands, &mut pool, entity, &mut projectile);
+            continue;
+        }
+
+        projectile.velocity.y -= projectile.spec.gravity * dt;
+        let step = projectile.velocity * dt;
+        transform.translation.x += step.x;
+        transform.translation.y += step.y;
+    }
+}
+
+fn detect_impacts(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionStarted>,
+    mut pool: ResMut<ProjectilePool>,
+    mut projectiles: Query<(&mut Projectile, &GlobalTransform)>,
+    targets: Query<(), With<Health>>,
+    mut damage: EventWriter<DamageEvent>,
+    mut impacts: EventWriter<ProjectileImpact>,
+) {
+    for CollisionStarted(lhs, rhs) in collisions.read() {
+        for (entity, other) in [(*lhs, *rhs), (*rhs, *lhs)] {
+            let Ok((mut projectile, transform)) = projectiles.get_mut(entity) else {
+                continue;
+            };
+
+            if !projectile.active || other == projectile.owner {
+                continue;
+            }
+
+            let position = transform.translation().xy();
+
+            if !targets.contains(other) {
+                impacts.send(ProjectileImpact {
+                    projectile: entity,
+                    owner: projectile.owner,
+                    target: None,
+                    position,
+                });
+                release(&mut commands, &mut pool, entity, &mut projectile);
+                continue;
+            }
+
+            if !projectile.already_hit.insert(other) {
+                continue;
+            }
+
+            let push = projectile.velocity.normalize_or_zero() * projectile.spec.knockback;
+            damage.send(DamageEvent {
+                target: other,
+                amount: projectile.spec.damage,
+                knockback: Vec2::new(push.x, push.y.max(0.0)),
+            });
+            impacts.send(ProjectileImpact {
+                projectile: entity,
+                owner: projectile.owner,
+                target: Some(other),
+                position,
+            });
+
+            if projectile.pierced >= projectile.spec.pierce {
+                release(&mut commands, &mut pool, entity, &mut projectile);
+            } else {
+                projectile.pierced += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::{HealthPlugin, Hurt};
+    use std::time::Duration;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_event::<CollisionStarted>()
+            .add_plugins((ProjectilePlugin, HealthPlugin));
+        // Runs Startup, which prewarms the pool
+        app.update();
+        app
+    }
+
+    fn tick(app: &mut App, seconds: f32) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs_f32(seconds));
+        app.world_mut().run_schedule(FixedUpdate);
+    }
+
+    fn fire(app: &mut App) {
+        app.world_mut().send_event(FireProjectile {
+            owner: Entity::PLACEHOLDER,
+            team: Team::Enemy,
+            origin: Vec2::ZERO,
+            direction: Vec2::X,
+            spec: ProjectileSpec::ENEMY_BOLT,
+        });
+    }
+
+    fn active(app: &mut App) -> Vec<Entity> {
+        app.world_mut()
+            .query::<(Entity, &Projectile)>()
+            .iter(app.world())
+            .filter(|(_, projectile)| projectile.active)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    fn available(app: &App) -> usize {
+        app.world().resource::<ProjectilePool>().available()
+    }
+
+    #[test]
+    fn released_projectiles_are_fired_again() {
+        let mut app = app();
+        assert_eq!(available(&app), PREWARMED);
+
+        fire(&mut app);
+        tick(&mut app, 0.0);
+        let first = active(&mut app);
+        assert_eq!(first.len(), 1);
+        assert_eq!(available(&app), PREWARMED - 1);
+
+        tick(&mut app, ProjectileSpec::ENEMY_BOLT.lifetime);
+        assert!(active(&mut app).is_empty());
+        assert_eq!(available(&app), PREWARMED);
+
+        fire(&mut app);
+        tick(&mut app, 0.0);
+        assert_eq!(active(&mut app), first);
+    }
+
+    #[test]
+    fn pool_grows_past_the_prewarmed_entities() {
+        let mut app = app();
+
+        for _ in 0..=PREWARMED {
+            fire(&mut app);
+        }
+        tick(&mut app, 0.0);
+
+        assert_eq!(active(&mut app).len(), PREWARMED + 1);
+        assert_eq!(available(&app), 0);
+    }
+
+    #[test]
+    fn projectiles_are_parked_when_their_lifetime_ends() {
+        let mut app = app();
+        let lifetime = ProjectileSpec::ENEMY_BOLT.lifetime;
+
+        fire(&mut app);
+        tick(&mut app, 0.0);
+        let projectile = active(&mut app)[0];
+
+        tick(&mut app, lifetime * 0.5);
+        assert_eq!(active(&mut app), vec![projectile]);
+
+        tick(&mut app, lifetime * 0.5);
+        let parked = app.world().entity(projectile);
+        assert!(active(&mut app).is_empty());
+        assert!(parked.contains::<ColliderDisabled>());
+        assert_eq!(parked.get::<Transform>().unwrap().translation, PARKED_AT);
+    }
+
+    #[test]
+    fn bolt_hitting_the_player_hurts_it() {
+        let mut app = app();
+        let player = app.world_mut().spawn(Health::new(3)).id();
+
+        fire(&mut app);
+        tick(&mut app, 0.0);
+        let bolt = active(&mut app)[0];
+
+        app.world_mut().send_event(CollisionStarted(bolt, player));
+        tick(&mut app, 0.0);
+
+        let hurt = app.world().resource::<Events<Hurt>>();
+        assert!(hurt
+            .iter_current_update_events()
+            .any(|Hurt(entity)| *entity == player));
+        assert_eq!(app.world().get::<Health>(player).unwrap().current, 2);
+        assert!(active(&mut app).is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+pub struct CursorTrackingPlugin;
+
+impl Plugin for CursorTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CursorPosition>();
+        app.add_systems(PreUpdate, track_cursor);
+    }
+}
+
+/// World position under the mouse, seen through the `Camera2d`. `None` while
+/// the cursor is outside the window.
+#[derive(Resource, Default, Debug)]
+pub struct CursorPosition(pub Option<Vec2>);
+
+fn track_cursor(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut cursor: ResMut<CursorPosition>,
+) {
+    cursor.0 = windows
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .and_then(|viewport_position| {
+            let (camera, camera_transform) = cameras.get_single().ok()?;
+            camera
+                .viewport_to_world_2d(camera_transform, viewport_position)
+                .ok()
+        });
+}
@@ -0,0 +1,195 @@
+//! `Decoration` entities from LDtk name a sprite in
+//! `assets/decorations.manifest.json`, which maps names to an image and an
+//! optional region of it. The images load with the manifest, so a missing
+//! file fails the manifest instead of leaving it half loaded.
+
+use std::collections::HashMap;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::Deserialize;
+
+use crate::json_asset::JsonAssetError;
+use crate::registry;
+
+pub struct DecorationPlugin;
+
+impl Plugin for DecorationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<DecorationManifest>()
+            .register_asset_loader(DecorationManifestLoader);
+        app.register_ldtk_entity::<DecorationBundle>(registry::DECORATION);
+        app.add_systems(Update, (report_failed_assets, show_decorations));
+    }
+}
+
+/// Loaded by `LoadingPlugin`; decorations stay empty without it.
+#[derive(AssetCollection, Resource)]
+pub struct DecorationAssets {
+    #[asset(path = "decorations.manifest.json")]
+    pub manifest: Handle<DecorationManifest>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NamedSprite {
+    pub image: Handle<Image>,
+    /// Region of the image in pixels, the whole image when `None`
+    pub rect: Option<Rect>,
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct DecorationManifest {
+    pub sprites: HashMap<String, NamedSprite>,
+}
+
+#[derive(Deserialize)]
+struct ManifestEntry {
+    path: String,
+    /// x, y, width, height
+    #[serde(default)]
+    rect: Option<[f32; 4]>,
+}
+
+#[derive(Deserialize)]
+struct ManifestFile {
+    sprites: HashMap<String, ManifestEntry>,
+}
+
+struct DecorationManifestLoader;
+
+impl AssetLoader for DecorationManifestLoader {
+    type Asset = DecorationManifest;
+    type Settings = ();
+    type Error = JsonAssetError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<DecorationManifest, JsonAssetError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(JsonAssetError::Io)?;
+        let file: ManifestFile = serde_json::from_slice(&bytes).map_err(JsonAssetError::Json)?;
+
+        let sprites = file
+            .sprites
+            .into_iter()
+            .map(|(name, entry)| {
+                let sprite = NamedSprite {
+                    image: load_context.load(entry.path),
+                    rect: entry
+                        .rect
+                        .map(|[x, y, width, height]| Rect::new(x, y, x + width, y + height)),
+                };
+                (name, sprite)
+            })
+            .collect();
+
+        Ok(DecorationManifest { sprites })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.json"]
+    }
+}
+
+#[derive(Component, Clone, Debug, Default)]
+pub struct Decoration {
+    /// Name of the sprite in the manifest
+    pub sprite: String,
+}
+
+impl Decoration {
+    fn from_fields(entity_instance: &EntityInstance) -> Self {
+        Decoration {
+            sprite: entity_instance
+                .get_string_field("sprite")
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct DecorationBundle {
+    #[with(Decoration::from_fields)]
+    decoration: Decoration,
+    sprite: Sprite,
+}
+
+/// Marks decorations already given their sprite, or found not to have one.
+#[derive(Component)]
+struct Decorated;
+
+fn show_decorations(
+    mut commands: Commands,
+    assets: Option<Res<DecorationAssets>>,
+    manifests: Res<Assets<DecorationManifest>>,
+    mut decorations: Query<(Entity, &Decoration, &mut Sprite), Without<Decorated>>,
+) {
+    let Some(manifest) = assets.and_then(|assets| manifests.get(&assets.manifest)) else {
+        return;
+    };
+
+    for (entity, decoration, mut sprite) in decorations.iter_mut() {
+        match manifest.sprites.get(&decoration.sprite) {
+            Some(named) => {
+                sprite.image = named.image.clone();
+                sprite.rect = named.rect;
+            }
+            None => warn!("No decoration sprite named `{}`", decoration.sprite),
+        }
+        commands.entity(entity).insert(Decorated);
+    }
+}
+
+fn report_failed_assets(
+    mut images: EventReader<AssetLoadFailedEvent<Image>>,
+    mut manifests: EventReader<AssetLoadFailedEvent<DecorationManifest>>,
+) {
+    for failure in images.read() {
+        error!("Could not load image {}: {}", failure.path, failure.error);
+    }
+    for failure in manifests.read() {
+        error!("Could not load {}: {}", failure.path, failure.error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_ldtk::ldtk::LdtkJson;
+
+    #[test]
+    fn placed_decorations_name_manifest_sprites() {
+        let manifest: ManifestFile =
+            serde_json::from_str(include_str!("../assets/decorations.manifest.json")).unwrap();
+        let project: LdtkJson =
+            serde_json::from_str(include_str!("../assets/gridvania.ldtk")).unwrap();
+
+        let decorations: Vec<Decoration> = project
+            .levels
+            .iter()
+            .flat_map(|level| level.layer_instances.iter().flatten())
+            .flat_map(|layer| layer.entity_instances.iter())
+            .filter(|entity| entity.identifier == registry::DECORATION)
+            .map(Decoration::from_fields)
+            .collect();
+
+        assert!(!decorations.is_empty());
+        for decoration in decorations {
+            assert!(
+                manifest.sprites.contains_key(&decoration.sprite),
+                "unknown sprite {}",
+                decoration.sprite
+            );
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use bevy::prelude::*;
+
+use super::{EnemyStats, PatrolRoute};
+use crate::character::Character;
+use crate::combat::projectile::{FireProjectile, Team};
+use crate::health::{DamageEvent, Died, KNOCKBACK};
+use crate::physics::{BodyVelocity, PhysicsQuery, RayFilter};
+
+const IDLE_TIME: f32 = 1.0;
+const ATTACK_WINDUP: f32 = 0.4;
+const ATTACK_COOLDOWN: f32 = 0.8;
+const HURT_TIME: f32 = 0.3;
+const CORPSE_TIME: f32 = 1.0;
+// How long the player may stay hidden before a chase is given up
+const CHASE_MEMORY: f32 = 2.0;
+const WAYPOINT_REACHED: f32 = 4.0;
+
+pub struct EnemyAiPlugin;
+
+impl Plugin for EnemyAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                react_to_damage,
+                sense_player,
+                update_state,
+                steer,
+                remove_dead,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum EnemyState {
+    Idle,
+    Patrol,
+    Chase,
+    Attack,
+    Hurt,
+    Dead,
+}
+
+#[derive(Component, Debug)]
+pub struct EnemyBrain {
+    pub state: EnemyState,
+    /// Seconds spent in the current state
+    pub elapsed: f32,
+    pub waypoint: usize,
+    pub sees_player: bool,
+    pub last_seen: Option<Vec2>,
+    unseen_for: f32,
+    attacked: bool,
+}
+
+impl EnemyBrain {
+    pub fn new(state: EnemyState) -> Self {
+        EnemyBrain {
+            state,
+            elapsed: 0.0,
+            waypoint: 0,
+            sees_player: false,
+            last_seen: None,
+            unseen_for: 0.0,
+            attacked: false,
+        }
+    }
+
+    fn transition(&mut self, state: EnemyState) {
+        if self.state != state {
+            debug!("Enemy {:?} -> {:?}", self.state, state);
+            self.state = state;
+            self.elapsed = 0.0;
+            self.attacked = false;
+        }
+    }
+}
+
+fn react_to_damage(
+    mut damage: EventReader<DamageEvent>,
+    mut died: EventReader<Died>,
+    mut brains: Query<&mut EnemyBrain>,
+) {
+    for event in damage.read() {
+        if let Ok(mut brain) = brains.get_mut(event.target) {
+            if brain.state != EnemyState::Dead {
+                brain.transition(EnemyState::Hurt);
+            }
+        }
+    }
+
+    for Died(entity) in died.read() {
+        if let Ok(mut brain) = brains.get_mut(*entity) {
+            brain.transition(EnemyState::Dead);
+        }
+    }
+}
+
+fn sense_player(
+    time: Res<Time>,
+    physics: PhysicsQuery,
+    player: Query<(Entity, &GlobalTransform), With<Character>>,
+    mut enemies: Query<(Entity, &GlobalTransform, &EnemyStats, &mut EnemyBrain)>,
+) {
+    let Ok((player_entity, player_transform)) = player.get_single() else {
+        return;
+    };
+    let player_position = player_transform.translation().xy();
+
+    for (entity, transform, stats, mut brain) in enemies.iter_mut() {
+        let position = transform.translation().xy();
+        let to_player = player_position - position;
+        let distance = to_player.length();
+
+        // Anything hit before the player means the walls are in the way
+        let sees_player = distance <= stats.sight_range
+            && physics
+                .cast_ray(
+                    position,
+                    to_player,
+                    distance,
+                    &RayFilter {
+                        exclude: Some(entity),
+                        ..default()
+                    },
+                )
+                .is_none_or(|hit| hit.entity == player_entity);
+
+        brain.sees_player = sees_player;
+        if sees_player {
+            brain.last_seen = Some(player_position);
+            brain.unseen_for = 0.0;
+        } else {
+            brain.unseen_for += time.delta_secs();
+        }
+    }
+}
+
+fn update_state(
+    time: Res<Time>,
+    player: Query<(Entity, &GlobalTransform), With<Character>>,
+    mut enemies: Query<(
+        Entity,
+        &GlobalTransform,
+        &EnemyStats,
+        &PatrolRoute,
+        &mut EnemyBrain,
+    )>,
+    mut damage: EventWriter<DamageEvent>,
+    mut fire: EventWriter<FireProjectile>,
+) {
+    let player = player.get_single().ok();
+
+    for (entity, transform, stats, patrol, mut brain) in enemies.iter_mut() {
+        brain.elapsed += time.delta_secs();
+        let position = transform.translation().xy();
+        let player_distance = player
+            .map(|(_, player_transform)| player_transform.translation().xy().distance(position));
+        let in_attack_range = player_distance.is_some_and(|d| d <= stats.attack_range);
+
+        match brain.state {
+            EnemyState::Idle => {
+                if brain.sees_player {
+                    brain.transition(EnemyState::Chase);
+                } else if brain.elapsed >= IDLE_TIME && !patrol.waypoints.is_empty() {
+                    brain.transition(EnemyState::Patrol);
+                }
+            }
+            EnemyState::Patrol => {
+                if brain.sees_player {
+                    brain.transition(EnemyState::Chase);
+                }
+            }
+            EnemyState::Chase => {
+                if in_attack_range && brain.sees_player {
+                    brain.transition(EnemyState::Attack);
+                } else if brain.unseen_for >= CHASE_MEMORY {
+                    brain.last_seen = None;
+                    brain.transition(EnemyState::Idle);
+                }
+            }
+            EnemyState::Attack => {
+                if !brain.attacked && brain.elapsed >= ATTACK_WINDUP {
+                    brain.attacked = true;
+                    if let Some((player_entity, player_transform)) =
+                        player.filter(|_| in_attack_range)
+                    {
+                        if let Some(spec) = stats.projectile {
+                            fire.send(FireProjectile {
+                                owner: entity,
+                                team: Team::Enemy,
+                                origin: position,
+                                direction: player_transform.translation().xy() - position,
+                                spec,
+                            });
+                        } else {
+                            let away = (player_transform.translation().x - position.x).signum();
+                            damage.send(DamageEvent {
+                                target: player_entity,
+                                amount: stats.attack_damage,
+                                knockback: Vec2::new(KNOCKBACK.x * away, KNOCKBACK.y),
+                            });
+                        }
+                    }
+                }
+                if brain.elapsed >= ATTACK_WINDUP + ATTACK_COOLDOWN {
+                    brain.transition(EnemyState::Chase);
+                }
+            }
+            EnemyState::Hurt => {
+                if brain.elapsed >= HURT_TIME {
+                    brain.transition(EnemyState::Chase);
+                }
+            }
+            EnemyState::Dead => {}
+        }
+    }
+}
+
+fn steer(
+    mut enemies: Query<(
+        &GlobalTransform,
+        &Transform,
+        &EnemyStats,
+        &PatrolRoute,
+        &mut EnemyBrain,
+        BodyVelocity,
+    )>,
+) {
+    for (global_transform, transform, stats, patrol, mut brain, mut velocity) in enemies.iter_mut()
+    {
+        let target_x = match brain.state {
+            EnemyState::Patrol => {
+                let Some(waypoint) = patrol.waypoints.get(brain.waypoint) else {
+                    continue;
+                };
+
+                // Waypoints live in the same space as the local transform
+                if (waypoint.x - transform.translation.x).abs() < WAYPOINT_REACHED {
+                    brain.waypoint = (brain.waypoint + 1) % patrol.waypoints.len();
+                    brain.transition(EnemyState::Idle);
+                    None
+                } else {
+                    let offset = waypoint.x - transform.translation.x;
+                    Some((
+                        global_transform.translation().x + offset,
+                        stats.patrol_speed,
+                    ))
+                }
+            }
+            EnemyState::Chase => brain.last_seen.map(|seen| (seen.x, stats.chase_speed)),
+            // Hurt keeps whatever knockback it was given
+            EnemyState::Hurt => continue,
+            EnemyState::Idle | EnemyState::Attack | EnemyState::Dead => None,
+        };
+
+        let linear = velocity.linear();
+        let x = match target_x {
+            Some((x, speed)) => (x - global_transform.translation().x).signum() * speed,
+            None => 0.0,
+        };
+        velocity.set_linear(Vec2::new(x, linear.y));
+    }
+}
+
+fn remove_dead(mut commands: Commands, enemies: Query<(Entity, &EnemyBrain)>) {
+    for (entity, brain) in enemies.iter() {
+        if brain.state == EnemyState::Dead && brain.elapsed >= CORPSE_TIME {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,146 @@
+pub mod ai;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::combat::projectile::ProjectileSpec;
+use crate::health::{ContactDamage, Health};
+use crate::physics::{self, layers, BodyKind};
+use crate::registry;
+use ai::{EnemyBrain, EnemyState};
+
+const GRID_SIZE: f32 = 16.0;
+const ENEMY_SIZE: Vec2 = Vec2::new(12.0, 14.0);
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<EnemyBundle>(registry::ENEMY);
+        app.add_systems(Update, on_add_enemy);
+        app.add_plugins(ai::EnemyAiPlugin);
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Enemy;
+
+/// Tuning for a single enemy. Distances are in pixels, speeds in pixels per second.
+#[derive(Component, Clone, Debug)]
+pub struct EnemyStats {
+    pub patrol_speed: f32,
+    pub chase_speed: f32,
+    pub sight_range: f32,
+    pub attack_range: f32,
+    pub attack_damage: i32,
+    /// Ranged enemies shoot this instead of striking in melee
+    pub projectile: Option<ProjectileSpec>,
+}
+
+impl EnemyStats {
+    /// Defaults, turned into a shooter when the LDtk `ranged` field is set.
+    fn from_fields(entity_instance: &EntityInstance) -> Self {
+        let ranged = entity_instance
+            .get_bool_field("ranged")
+            .copied()
+            .unwrap_or(false);
+
+        if ranged {
+            EnemyStats {
+                attack_range: 120.0,
+                projectile: Some(ProjectileSpec::ENEMY_BOLT),
+                ..default()
+            }
+        } else {
+            EnemyStats::default()
+        }
+    }
+}
+
+impl Default for EnemyStats {
+    fn default() -> Self {
+        EnemyStats {
+            patrol_speed: 40.0,
+            chase_speed: 90.0,
+            sight_range: 160.0,
+            attack_range: 20.0,
+            attack_damage: 1,
+            projectile: None,
+        }
+    }
+}
+
+/// Waypoints relative to where the enemy spawned, filled from the LDtk `patrol`
+/// points field. Resolved to level-local positions once the enemy spawns.
+#[derive(Component, Clone, Debug, Default)]
+pub struct PatrolRoute {
+    offsets: Vec<Vec2>,
+    pub waypoints: Vec<Vec2>,
+}
+
+impl PatrolRoute {
+    fn from_patrol_field(entity_instance: &EntityInstance) -> Self {
+        let Ok(points) = entity_instance.get_points_field("patrol") else {
+            return PatrolRoute::default();
+        };
+
+        // LDtk grid coordinates grow downwards
+        let offsets = points
+            .into_iter()
+            .map(|point| {
+                let delta = *point - entity_instance.grid;
+                Vec2::new(delta.x as f32, -delta.y as f32) * GRID_SIZE
+            })
+            .collect();
+
+        PatrolRoute {
+            offsets,
+            waypoints: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct EnemyBundle {
+    enemy: Enemy,
+    #[with(Health::from_hp_field)]
+    health: Health,
+    #[with(PatrolRoute::from_patrol_field)]
+    patrol: PatrolRoute,
+}
+
+fn on_add_enemy(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Transform, &EntityInstance, &mut PatrolRoute), Added<Enemy>>,
+) {
+    for (entity, transform, entity_instance, mut patrol) in query.iter_mut() {
+        let spawn = transform.translation.xy();
+        patrol.waypoints = patrol
+            .offsets
+            .iter()
+            .map(|offset| spawn + *offset)
+            .collect();
+
+        let stats = EnemyStats::from_fields(entity_instance);
+        // Shooters are tinted so they can be told apart before they fire
+        let color = if stats.projectile.is_some() {
+            Color::srgb(0.75, 0.35, 0.8)
+        } else {
+            Color::srgb(0.85, 0.3, 0.25)
+        };
+
+        commands.entity(entity).insert((
+            stats,
+            Sprite::from_color(color, ENEMY_SIZE),
+            EnemyBrain::new(EnemyState::Idle),
+            ContactDamage(1),
+            physics::capsule(Vec2::new(0.0, -4.0), Vec2::new(0.0, 0.0), 6.0),
+            physics::velocity(),
+            physics::lock_rotation(),
+            physics::body(BodyKind::Dynamic),
+            physics::friction(0.0),
+            physics::report_collisions(),
+            physics::collision_layers(layers::ENEMY, layers::ALL),
+        ));
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::character::controller_kinematic::KinematicImpulse;
+use crate::character::Character;
+use crate::level_settings::InSafeRoom;
+use crate::physics::{BodyVelocity, CollisionEnded, CollisionStarted};
+
+/// Default push applied to whoever gets hit, mirrored to point away from the attacker
+pub const KNOCKBACK: Vec2 = Vec2::new(150.0, 100.0);
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>();
+        app.add_event::<Died>();
+        app.add_event::<Hurt>();
+        app.init_resource::<Contacts>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                track_contacts,
+                contact_damage,
+                tick_invulnerability,
+                apply_damage,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+    /// Seconds of invulnerability after taking a hit
+    pub invulnerability: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health::new(5)
+    }
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Health {
+            current: max,
+            max,
+            invulnerability: 0.0,
+        }
+    }
+
+    pub fn with_invulnerability(mut self, seconds: f32) -> Self {
+        self.invulnerability = seconds;
+        self
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0
+    }
+
+    /// Reads the `HP` field of an LDtk entity, defaulting to 5.
+    pub fn from_hp_field(entity_instance: &EntityInstance) -> Self {
+        let hp = entity_instance.get_int_field("HP").copied().unwrap_or(5);
+        Health::new(hp.max(1))
+    }
+}
+
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+/// Damage dealt to the player for every tick it touches this entity.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ContactDamage(pub i32);
+
+/// Attacker/player pairs currently touching.
+#[derive(Resource, Default)]
+struct Contacts(HashSet<(Entity, Entity)>);
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+    pub knockback: Vec2,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Died(pub Entity);
+
+/// Damage was taken without dying.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Hurt(pub Entity);
+
+fn track_contacts(
+    mut started: EventReader<CollisionStarted>,
+    mut ended: EventReader<CollisionEnded>,
+    mut contacts: ResMut<Contacts>,
+    attackers: Query<(), With<ContactDamage>>,
+    players: Query<(), With<Character>>,
+) {
+    let pair = |lhs: Entity, rhs: Entity| {
+        if attackers.contains(lhs) && players.contains(rhs) {
+            Some((lhs, rhs))
+        } else if attackers.contains(rhs) && players.contains(lhs) {
+            Some((rhs, lhs))
+        } else {
+            None
+        }
+    };
+
+    for CollisionStarted(lhs, rhs) in started.read() {
+        contacts.0.extend(pair(*lhs, *rhs));
+    }
+
+    for CollisionEnded(lhs, rhs) in ended.read() {
+        if let Some(pair) = pair(*lhs, *rhs) {
+            contacts.0.remove(&pair);
+        }
+    }
+}
+
+fn contact_damage(
+    mut contacts: ResMut<Contacts>,
+    attackers: Query<(&GlobalTransform, &ContactDamage, Option<&Health>)>,
+    players: Query<&GlobalTransform, (With<Character>, Without<Invulnerable>, Without<InSafeRoom>)>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    // Forget pairs whose attacker was despawned without a collision end
+    contacts
+        .0
+        .retain(|(attacker, _)| attackers.contains(*attacker));
+
+    for (attacker, player) in contacts.0.iter() {
+        let (Ok((attacker_transform, contact, health)), Ok(player_transform)) =
+            (attackers.get(*attacker), players.get(*player))
+        else {
+            continue;
+        };
+
+        if health.is_some_and(Health::is_dead) {
+            continue;
+        }
+
+        let away = (player_transform.translation().x - attacker_transform.translation().x).signum();
+        damage.send(DamageEvent {
+            target: *player,
+            amount: contact.0,
+            knockback: Vec2::new(KNOCKBACK.x * away, KNOCKBACK.y),
+        });
+    }
+}
+
+fn tick_invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (entity, mut invulnerable) in query.iter_mut() {
+        if invulnerable.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut events: EventReader<DamageEvent>,
+    mut targets: Query<
+        (
+            &mut Health,
+            Option<BodyVelocity>,
+            Option<&mut KinematicImpulse>,
+        ),
+        (Without<Invulnerable>, Without<InSafeRoom>),
+    >,
+    mut died: EventWriter<Died>,
+    mut hurt: EventWriter<Hurt>,
+) {
+    for event in events.read() {
+        let Ok((mut health, velocity, kinematic_impulse)) = targets.get_mut(event.target) else {
+            continue;
+        };
+
+        if health.is_dead() {
+            continue;
+        }
+
+        health.current -= event.amount;
+        debug!(
+            "{:?} took {} damage ({}/{})",
+            event.target, event.amount, health.current, health.max
+        );
+
+        if event.knockback != Vec2::ZERO {
+            if let Some(mut velocity) = velocity {
+                velocity.set_linear(event.knockback);
+            }
+            if let Some(mut impulse) = kinematic_impulse {
+                impulse.0 = event.knockback;
+            }
+        }
+
+        if health.is_dead() {
+            died.send(Died(event.target));
+            continue;
+        }
+
+        hurt.send(Hurt(event.target));
+        if health.invulnerability > 0.0 {
+            commands
+                .entity(event.target)
+                .insert(Invulnerable(Timer::from_seconds(
+                    health.invulnerability,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! `Item` entities from LDtk, picked up by touching them. Shop prices are
+//! not charged yet.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::character::Character;
+use crate::physics::{self, layers, BodyKind, CollisionStarted};
+use crate::registry;
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<ItemBundle>(registry::ITEM);
+        app.add_event::<ItemPicked>();
+        app.add_systems(Update, on_add_item);
+        app.add_systems(FixedUpdate, pick_up_items);
+    }
+}
+
+#[derive(Component, Clone, Debug, Default)]
+pub struct Item {
+    /// Value of the LDtk `type` enum field, e.g. `Gold`
+    pub kind: String,
+    pub count: i32,
+}
+
+impl Item {
+    fn from_fields(entity_instance: &EntityInstance) -> Self {
+        Item {
+            kind: entity_instance
+                .get_maybe_enum_field("type")
+                .ok()
+                .cloned()
+                .flatten()
+                .unwrap_or_default(),
+            count: entity_instance.get_int_field("count").copied().unwrap_or(1),
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ItemPicked {
+    pub picker: Entity,
+    pub kind: String,
+    pub count: i32,
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct ItemBundle {
+    #[with(Item::from_fields)]
+    item: Item,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+}
+
+fn on_add_item(mut commands: Commands, query: Query<Entity, Added<Item>>) {
+    for entity in query.iter() {
+        commands.entity(entity).insert((
+            physics::cuboid(8.0, 8.0),
+            physics::body(BodyKind::Fixed),
+            physics::sensor(),
+            physics::report_collisions(),
+            physics::collision_layers(layers::WORLD, layers::PLAYER),
+        ));
+    }
+}
+
+fn pick_up_items(
+    mut commands: Commands,
+    mut started: EventReader<CollisionStarted>,
+    items: Query<&Item>,
+    players: Query<(), With<Character>>,
+    mut picked: EventWriter<ItemPicked>,
+) {
+    for CollisionStarted(lhs, rhs) in started.read() {
+        let (item_entity, picker) = if players.contains(*rhs) {
+            (*lhs, *rhs)
+        } else if players.contains(*lhs) {
+            (*rhs, *lhs)
+        } else {
+            continue;
+        };
+        let Ok(item) = items.get(item_entity) else {
+            continue;
+        };
+
+        debug!("Picked up {} x{}", item.kind, item.count);
+        picked.send(ItemPicked {
+            picker,
+            kind: item.kind.clone(),
+            count: item.count,
+        });
+        commands.entity(item_entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// Registers `A` as an asset loaded from JSON files with the given extensions,
+/// e.g. `boss.json` for `bosses/boss_room.boss.json`.
+pub struct JsonAssetPlugin<A> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> A>,
+}
+
+impl<A> JsonAssetPlugin<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        JsonAssetPlugin {
+            extensions,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<A: Asset + DeserializeOwned> Plugin for JsonAssetPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<A>()
+            .register_asset_loader(JsonAssetLoader::<A> {
+                extensions: self.extensions,
+                _marker: PhantomData,
+            });
+    }
+}
+
+struct JsonAssetLoader<A> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> A>,
+}
+
+#[derive(Debug)]
+pub enum JsonAssetError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for JsonAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonAssetError::Io(error) => write!(f, "could not read asset: {error}"),
+            JsonAssetError::Json(error) => write!(f, "invalid JSON: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for JsonAssetError {}
+
+impl<A: Asset + DeserializeOwned> AssetLoader for JsonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = JsonAssetError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<A, JsonAssetError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(JsonAssetError::Io)?;
+
+        serde_json::from_slice(&bytes).map_err(JsonAssetError::Json)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -1,4 +1,4 @@
-use avian2d::prelude::*;
+use crate::physics::BodyVelocity;
 use bevy::prelude::*;
 
 pub struct KeyboardRotationPlugin;
@@ -13,13 +13,14 @@ impl Plugin for KeyboardRotationPlugin {
 }
 
 fn rotate_platform(
-    mut query: Query<(&mut AngularVelocity, &Rotatable)>,
+    mut query: Query<(BodyVelocity, &Rotatable)>,
     keys: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
 ) {
     if keys.pressed(KeyCode::KeyR) {
         for (mut vel, Rotatable(speed)) in query.iter_mut() {
-            vel.0 += *speed * time.delta_secs();
+            let angular = vel.angular() + *speed * time.delta_secs();
+            vel.set_angular(angular);
         }
     }
 }
@@ -0,0 +1,595 @@
+//! Writes runtime edits back into a copy of the LDtk project. The project is
+//! edited as raw JSON so every field the game does not know about, including
+//! auto-layer rules, is kept as is. Press Ctrl+S while the tile editor is on.
+//!
+//! IntGrid cells, removed entities and entities placed in the editor are
+//! written. Placed entities get their definition's default field values.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::navigation::COLLISIONS_LAYER;
+use crate::tile_grid::{AddedEntity, LevelEdits, TileEditor};
+
+const ASSETS_DIR: &str = "assets";
+
+pub struct LdtkExportPlugin;
+
+impl Plugin for LdtkExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, export_on_save);
+    }
+}
+
+/// `foo.ldtk` becomes `foo.edited.ldtk`, next to the original.
+pub fn edited_path(source: &Path) -> PathBuf {
+    source.with_extension("edited.ldtk")
+}
+
+/// Reads the project at `source`, applies `edits` and writes the result to
+/// `destination` with LDtk's own tab indentation.
+pub fn export_edits(source: &Path, destination: &Path, edits: &LevelEdits) -> io::Result<()> {
+    let contents = fs::read_to_string(source)?;
+    let mut project: Value = serde_json::from_str(&contents).map_err(io::Error::from)?;
+
+    apply_edits(&mut project, edits)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    let mut output = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"\t");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+    project
+        .serialize(&mut serializer)
+        .map_err(io::Error::from)?;
+
+    fs::write(destination, output)
+}
+
+/// Applies `edits` to a parsed LDtk project.
+pub fn apply_edits(project: &mut Value, edits: &LevelEdits) -> Result<(), String> {
+    let entity_defs = project["defs"]["entities"].clone();
+    let mut added_to_toc = Vec::new();
+
+    // Single-world projects keep levels at the root, multi-world ones per world
+    let root_iid = project["iid"].clone();
+    if let Some(Value::Array(levels)) = project.get_mut("levels") {
+        for level in levels.iter_mut() {
+            added_to_toc.extend(apply_level_edits(level, edits, &entity_defs, &root_iid)?);
+        }
+    }
+    if let Some(Value::Array(worlds)) = project.get_mut("worlds") {
+        for world in worlds.iter_mut() {
+            let world_iid = world["iid"].clone();
+            if let Some(Value::Array(levels)) = world.get_mut("levels") {
+                for level in levels.iter_mut() {
+                    added_to_toc.extend(apply_level_edits(level, edits, &entity_defs, &world_iid)?);
+                }
+            }
+        }
+    }
+
+    let removed_iids: HashSet<&str> = edits
+        .removed_entities
+        .values()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+
+    // The table of contents lists every entity instance too
+    if let Some(Value::Array(toc)) = project.get_mut("toc") {
+        for entry in toc.iter_mut() {
+            for key in ["instances", "instancesData"] {
+                if let Some(Value::Array(instances)) = entry.get_mut(key) {
+                    instances.retain(|instance| {
+                        let entity_iid = instance
+                            .get("entityIid")
+                            .or_else(|| instance.pointer("/iids/entityIid"))
+                            .and_then(Value::as_str);
+                        entity_iid.is_none_or(|iid| !removed_iids.contains(iid))
+                    });
+                }
+            }
+        }
+
+        for (identifier, iids, data) in added_to_toc {
+            let index = match toc
+                .iter()
+                .position(|entry| entry["identifier"] == identifier)
+            {
+                Some(index) => index,
+                None => {
+                    toc.push(json!({
+                        "identifier": identifier,
+                        "instances": [],
+                        "instancesData": []
+                    }));
+                    toc.len() - 1
+                }
+            };
+            if let Some(Value::Array(instances)) = toc[index].get_mut("instances") {
+                instances.push(iids);
+            }
+            if let Some(Value::Array(instances)) = toc[index].get_mut("instancesData") {
+                instances.push(data);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A placed entity's identifier with the `instances` and `instancesData`
+/// entries listing it in the table of contents.
+type TocEntry = (String, Value, Value);
+
+/// Returns the table of contents entries of the entities added to `level`.
+fn apply_level_edits(
+    level: &mut Value,
+    edits: &LevelEdits,
+    entity_defs: &Value,
+    world_iid: &Value,
+) -> Result<Vec<TocEntry>, String> {
+    let Some(iid) = level["iid"].as_str() else {
+        return Ok(Vec::new());
+    };
+    let cells = edits.cells.get(iid);
+    let removed = edits.removed_entities.get(iid);
+    let added = edits
+        .added_entities
+        .get(iid)
+        .filter(|added| !added.is_empty());
+    if cells.is_none() && removed.is_none() && added.is_none() {
+        return Ok(Vec::new());
+    }
+
+    if !level["externalRelPath"].is_null() {
+        return Err(format!(
+            "level {} is stored in a separate file",
+            level["identifier"]
+        ));
+    }
+
+    let level_iid = level["iid"].clone();
+    let level_origin = (
+        level["worldX"].as_i64().unwrap_or(0),
+        level["worldY"].as_i64().unwrap_or(0),
+    );
+    let Some(layers) = level["layerInstances"].as_array_mut() else {
+        return Ok(Vec::new());
+    };
+
+    for layer in layers.iter_mut() {
+        if let (Some(cells), Some(COLLISIONS_LAYER)) = (cells, layer["__identifier"].as_str()) {
+            set_int_grid_cells(layer, cells)?;
+        }
+
+        if let (Some(removed), Some(instances)) = (removed, layer["entityInstances"].as_array_mut())
+        {
+            instances.retain(|instance| {
+                instance["iid"]
+                    .as_str()
+                    .is_none_or(|entity_iid| !removed.contains(entity_iid))
+            });
+        }
+    }
+
+    let Some(added) = added else {
+        return Ok(Vec::new());
+    };
+    // Placed entities go to the first entity layer, the one the editor shows
+    let layer = layers
+        .iter_mut()
+        .find(|layer| layer["__type"] == "Entities")
+        .ok_or_else(|| format!("level {} has no entity layer", level_iid))?;
+
+    let mut toc = Vec::new();
+    for entity in added {
+        let definition = entity_defs
+            .as_array()
+            .and_then(|defs| {
+                defs.iter()
+                    .find(|def| def["identifier"] == entity.identifier)
+            })
+            .ok_or_else(|| format!("no entity definition for {}", entity.identifier))?;
+        let instance = entity_instance(definition, entity, layer, level_origin)?;
+
+        let iids = json!({
+            "worldIid": world_iid,
+            "levelIid": level_iid,
+            "layerIid": layer["iid"],
+            "entityIid": entity.iid
+        });
+        let data = json!({
+            "iids": iids,
+            "worldX": instance["__worldX"],
+            "worldY": instance["__worldY"],
+            "widPx": instance["width"],
+            "heiPx": instance["height"],
+            "fields": {}
+        });
+        toc.push((entity.identifier.clone(), iids, data));
+
+        layer["entityInstances"]
+            .as_array_mut()
+            .ok_or("entity layer has no entityInstances")?
+            .push(instance);
+    }
+
+    Ok(toc)
+}
+
+/// An `entityInstances` entry for `entity`, laid out like LDtk writes them.
+/// The pivot snaps to the cell like it does in LDtk.
+fn entity_instance(
+    definition: &Value,
+    entity: &AddedEntity,
+    layer: &Value,
+    level_origin: (i64, i64),
+) -> Result<Value, String> {
+    let grid_size = layer["__gridSize"]
+        .as_i64()
+        .ok_or("layer has no __gridSize")?;
+    let width = layer["__cWid"].as_i64().ok_or("layer has no __cWid")?;
+    let height = layer["__cHei"].as_i64().ok_or("layer has no __cHei")?;
+
+    // `GridCoords` start at the bottom, LDtk grids at the top
+    let (x, y) = (entity.coords.x as i64, height - 1 - entity.coords.y as i64);
+    if x < 0 || y < 0 || x >= width || y >= height {
+        return Err(format!("{} is outside the layer", entity.identifier));
+    }
+
+    let pivot_x = definition["pivotX"].as_f64().unwrap_or(0.0);
+    let pivot_y = definition["pivotY"].as_f64().unwrap_or(0.0);
+    let px = (
+        x * grid_size + (pivot_x * grid_size as f64).round() as i64,
+        y * grid_size + (pivot_y * grid_size as f64).round() as i64,
+    );
+
+    let fields: Vec<Value> = definition["fieldDefs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|field| {
+            json!({
+                "__identifier": field["identifier"],
+                "__type": field["__type"],
+                "__value": default_field_value(field),
+                "__tile": null,
+                "defUid": field["uid"],
+                "realEditorValues": []
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "__identifier": entity.identifier,
+        "__grid": [x, y],
+        "__pivot": [pivot_x, pivot_y],
+        "__tags": definition["tags"],
+        "__tile": definition["tileRect"],
+        "__smartColor": definition["color"],
+        "iid": entity.iid,
+        "width": definition["width"],
+        "height": definition["height"],
+        "defUid": definition["uid"],
+        "px": [px.0, px.1],
+        "fieldInstances": fields,
+        "__worldX": level_origin.0 + px.0,
+        "__worldY": level_origin.1 + px.1
+    }))
+}
+
+/// The value LDtk gives a new instance of `field`: its default override, or
+/// the zero value of its type unless it can be null.
+fn default_field_value(field: &Value) -> Value {
+    if let Some(value) = field.pointer("/defaultOverride/params/0") {
+        return value.clone();
+    }
+
+    let field_type = field["__type"].as_str().unwrap_or_default();
+    let nullable = field["canBeNull"].as_bool().unwrap_or(true);
+    match field_type {
+        _ if field_type.starts_with("Array<") => json!([]),
+        "Int" | "Float" if !nullable => json!(0),
+        "Bool" => json!(false),
+        _ => Value::Null,
+    }
+}
+
+/// `cells` use `GridCoords` (origin bottom-left); `intGridCsv` is stored
+/// row by row from the top.
+fn set_int_grid_cells(layer: &mut Value, cells: &HashMap<GridCoords, i32>) -> Result<(), String> {
+    let width = layer["__cWid"].as_i64().ok_or("layer has no __cWid")?;
+    let height = layer["__cHei"].as_i64().ok_or("layer has no __cHei")?;
+    let csv = layer["intGridCsv"]
+        .as_array_mut()
+        .ok_or("layer has no intGridCsv")?;
+
+    for (coords, value) in cells {
+        let (x, y) = (coords.x as i64, coords.y as i64);
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return Err(format!("cell {:?} is outside the layer", coords));
+        }
+
+        let index = ((height - 1 - y) * width + x) as usize;
+        csv[index] = Value::from(*value);
+    }
+
+    Ok(())
+}
+
+fn export_on_save(
+    keys: Res<ButtonInput<KeyCode>>,
+    editor: Res<TileEditor>,
+    edits: Res<LevelEdits>,
+    asset_server: Res<AssetServer>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !editor.enabled || !ctrl || !keys.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+
+    let Some(asset_path) = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| asset_server.get_path(AssetId::<LdtkProject>::from(handle)))
+    else {
+        return;
+    };
+
+    let source = Path::new(ASSETS_DIR).join(asset_path.path());
+    let destination = edited_path(&source);
+
+    match export_edits(&source, &destination, &edits) {
+        Ok(()) => info!("Exported edits to {}", destination.display()),
+        Err(error) => error!("Could not export {}: {}", destination.display(), error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL_IID: &str = "level";
+
+    /// A 3x2 level with two entities, cells listed from the top row.
+    fn project() -> Value {
+        json!({
+            "iid": "world",
+            "defs": {
+                "entities": [{
+                    "identifier": "Enemy",
+                    "uid": 10,
+                    "width": 16,
+                    "height": 16,
+                    "pivotX": 0.5,
+                    "pivotY": 1,
+                    "tags": ["actor"],
+                    "tileRect": null,
+                    "color": "#E0533D",
+                    "fieldDefs": [
+                        {
+                            "identifier": "HP",
+                            "__type": "Int",
+                            "uid": 11,
+                            "canBeNull": false,
+                            "defaultOverride": { "id": "V_Int", "params": [3] }
+                        },
+                        {
+                            "identifier": "patrol",
+                            "__type": "Array<Point>",
+                            "uid": 12,
+                            "canBeNull": false,
+                            "defaultOverride": null
+                        }
+                    ]
+                }]
+            },
+            "levels": [{
+                "iid": LEVEL_IID,
+                "identifier": "Test",
+                "worldX": 256,
+                "worldY": 32,
+                "externalRelPath": null,
+                "layerInstances": [
+                    {
+                        "__identifier": "Entities",
+                        "__type": "Entities",
+                        "__cWid": 3,
+                        "__cHei": 2,
+                        "__gridSize": 16,
+                        "iid": "entities",
+                        "entityInstances": [{ "iid": "enemy" }, { "iid": "item" }]
+                    },
+                    {
+                        "__identifier": COLLISIONS_LAYER,
+                        "__cWid": 3,
+                        "__cHei": 2,
+                        "intGridCsv": [0, 0, 0, 1, 1, 1],
+                        "autoLayerTiles": [{ "px": [0, 16] }]
+                    }
+                ]
+            }],
+            "toc": [{
+                "identifier": "Enemy",
+                "instancesData": [{ "iids": { "entityIid": "enemy" } }]
+            }]
+        })
+    }
+
+    fn edits(cells: &[(GridCoords, i32)]) -> LevelEdits {
+        let mut edits = LevelEdits::default();
+        edits
+            .cells
+            .insert(LEVEL_IID.to_string(), cells.iter().copied().collect());
+        edits
+    }
+
+    #[test]
+    fn cells_are_flipped_into_csv_rows() {
+        let mut project = project();
+        let edits = edits(&[(GridCoords::new(0, 1), 3), (GridCoords::new(2, 0), 0)]);
+
+        apply_edits(&mut project, &edits).unwrap();
+
+        let layer = &project["levels"][0]["layerInstances"][1];
+        assert_eq!(layer["intGridCsv"], json!([3, 0, 0, 1, 1, 0]));
+        // Everything else about the layer is left alone
+        assert_eq!(layer["autoLayerTiles"], json!([{ "px": [0, 16] }]));
+    }
+
+    #[test]
+    fn cells_outside_the_layer_are_rejected() {
+        for coords in [
+            GridCoords::new(3, 0),
+            GridCoords::new(0, 2),
+            GridCoords::new(-1, 0),
+        ] {
+            let mut project = project();
+            assert!(apply_edits(&mut project, &edits(&[(coords, 1)])).is_err());
+        }
+    }
+
+    #[test]
+    fn removed_entities_leave_the_level_and_toc() {
+        let mut project = project();
+        let mut edits = LevelEdits::default();
+        edits
+            .removed_entities
+            .insert(LEVEL_IID.to_string(), HashSet::from(["enemy".to_string()]));
+
+        apply_edits(&mut project, &edits).unwrap();
+
+        assert_eq!(
+            project["levels"][0]["layerInstances"][0]["entityInstances"],
+            json!([{ "iid": "item" }])
+        );
+        assert_eq!(project["toc"][0]["instancesData"], json!([]));
+    }
+
+    #[test]
+    fn placed_entities_get_instances_and_toc_entries() {
+        let mut project = project();
+        let mut edits = LevelEdits::default();
+        edits.added_entities.insert(
+            LEVEL_IID.to_string(),
+            vec![AddedEntity {
+                iid: "placed".to_string(),
+                identifier: "Enemy".to_string(),
+                coords: GridCoords::new(2, 1),
+            }],
+        );
+
+        apply_edits(&mut project, &edits).unwrap();
+
+        // Top-right cell, pivot at the middle of its bottom edge
+        let instances = &project["levels"][0]["layerInstances"][0]["entityInstances"];
+        assert_eq!(instances.as_array().unwrap().len(), 3);
+        assert_eq!(
+            instances[2],
+            json!({
+                "__identifier": "Enemy",
+                "__grid": [2, 0],
+                "__pivot": [0.5, 1.0],
+                "__tags": ["actor"],
+                "__tile": null,
+                "__smartColor": "#E0533D",
+                "iid": "placed",
+                "width": 16,
+                "height": 16,
+                "defUid": 10,
+                "px": [40, 16],
+                "fieldInstances": [
+                    {
+                        "__identifier": "HP",
+                        "__type": "Int",
+                        "__value": 3,
+                        "__tile": null,
+                        "defUid": 11,
+                        "realEditorValues": []
+                    },
+                    {
+                        "__identifier": "patrol",
+                        "__type": "Array<Point>",
+                        "__value": [],
+                        "__tile": null,
+                        "defUid": 12,
+                        "realEditorValues": []
+                    }
+                ],
+                "__worldX": 296,
+                "__worldY": 48
+            })
+        );
+        // Listed next to the enemy the project already had
+        assert_eq!(
+            project["toc"][0]["instancesData"][1],
+            json!({
+                "iids": {
+                    "worldIid": "world",
+                    "levelIid": LEVEL_IID,
+                    "layerIid": "entities",
+                    "entityIid": "placed"
+                },
+                "worldX": 296,
+                "worldY": 48,
+                "widPx": 16,
+                "heiPx": 16,
+                "fields": {}
+            })
+        );
+    }
+
+    #[test]
+    fn placed_entities_need_a_definition_and_a_cell() {
+        for (identifier, coords) in [
+            ("Enemy", GridCoords::new(3, 0)),
+            ("Boss", GridCoords::new(0, 0)),
+        ] {
+            let mut project = project();
+            let mut edits = LevelEdits::default();
+            edits.added_entities.insert(
+                LEVEL_IID.to_string(),
+                vec![AddedEntity {
+                    iid: "placed".to_string(),
+                    identifier: identifier.to_string(),
+                    coords,
+                }],
+            );
+
+            assert!(apply_edits(&mut project, &edits).is_err());
+        }
+    }
+
+    #[test]
+    fn export_round_trips_through_a_file() {
+        let dir = std::env::temp_dir().join(format!("ldtk-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("test.ldtk");
+        fs::write(&source, project().to_string()).unwrap();
+        let destination = edited_path(&source);
+
+        let edits = edits(&[(GridCoords::new(1, 1), 2)]);
+        export_edits(&source, &destination, &edits).unwrap();
+
+        let exported: Value =
+            serde_json::from_str(&fs::read_to_string(&destination).unwrap()).unwrap();
+        let mut expected = project();
+        apply_edits(&mut expected, &edits).unwrap();
+        assert_eq!(exported, expected);
+        assert_eq!(
+            exported["levels"][0]["layerInstances"][1]["intGridCsv"],
+            json!([0, 2, 0, 1, 1, 1])
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
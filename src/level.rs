@@ -1,62 +1,454 @@
+use std::collections::HashMap;
+
+use crate::character::controller_kinematic::CAPSULE_CENTER;
 use crate::character::Character;
+use crate::health::Died;
+use crate::registry;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::{LdtkJson, Level};
 use bevy_ecs_ldtk::prelude::*;
 
+// Side of a `LevelIndex` grid cell in world pixels
+const INDEX_CELL_SIZE: f32 = 256.0;
+// How far past a seam the player has to be before the next level is selected
+const SELECTION_HYSTERESIS: f32 = 8.0;
+
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_level_selection)
-            .add_systems(Update, restart_level);
+        app.init_resource::<LevelIndex>()
+            .add_event::<LevelEntered>()
+            .add_event::<LevelExited>()
+            .add_event::<RestartRequested>()
+            .add_systems(Update, index_levels.before(update_level_selection))
+            .add_systems(
+                Update,
+                (update_level_selection, send_level_transitions).chain(),
+            )
+            .add_systems(
+                Update,
+                (request_restart, restart_on_player_death, restart_level)
+                    .chain()
+                    // The player is still where it died until the respawn
+                    .after(update_level_selection),
+            );
     }
 }
 
-pub fn update_level_selection(
-    level_query: Query<(&LevelIid, &Transform), Without<Character>>,
-    player_query: Query<&Transform, With<Character>>,
-    mut level_selection: ResMut<LevelSelection>,
+/// The selected level changed to this one, including the first level of a run.
+#[derive(Event, Debug, Clone)]
+pub struct LevelEntered(pub LevelIid);
+
+/// The selected level is no longer this one.
+#[derive(Event, Debug, Clone)]
+pub struct LevelExited(pub LevelIid);
+
+/// Respawns the world; sent on R and when the player dies.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestartRequested;
+
+/// Where a level sits in the world, without going through the LDtk asset.
+#[derive(Clone, Debug)]
+pub struct IndexedLevel {
+    pub iid: String,
+    pub identifier: String,
+    pub uid: i32,
+    pub indices: LevelIndices,
+    /// World bounds, in the same space as the spawned level entities
+    pub rect: Rect,
+}
+
+impl IndexedLevel {
+    pub fn is_selected(&self, selection: &LevelSelection) -> bool {
+        match selection {
+            LevelSelection::Identifier(identifier) => *identifier == self.identifier,
+            LevelSelection::Indices(indices) => *indices == self.indices,
+            LevelSelection::Iid(iid) => iid.to_string() == self.iid,
+            LevelSelection::Uid(uid) => *uid == self.uid,
+        }
+    }
+}
+
+/// Level bounds of the loaded project, rebuilt whenever the project asset
+/// (re)loads. Point queries only look at the levels overlapping one grid cell.
+#[derive(Resource, Default, Debug)]
+pub struct LevelIndex {
+    project: Option<AssetId<LdtkProject>>,
+    levels: Vec<IndexedLevel>,
+    by_iid: HashMap<String, usize>,
+    grid: HashMap<IVec2, Vec<usize>>,
+}
+
+impl LevelIndex {
+    pub fn from_project(project: &LdtkJson) -> Self {
+        let root = project
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, raw)| (LevelIndices::in_root(level), raw));
+        let worlds = project.worlds.iter().enumerate().flat_map(|(world, w)| {
+            w.levels
+                .iter()
+                .enumerate()
+                .map(move |(level, raw)| (LevelIndices::in_world(world, level), raw))
+        });
+
+        let mut index = LevelIndex::default();
+        for (indices, level) in root.chain(worlds) {
+            index.insert(indices, level);
+        }
+        index
+    }
+
+    fn insert(&mut self, indices: LevelIndices, level: &Level) {
+        // LDtk's y axis points down; levels are spawned from their bottom-left corner
+        let min = Vec2::new(level.world_x as f32, -(level.world_y + level.px_hei) as f32);
+        let rect = Rect::from_corners(
+            min,
+            min + Vec2::new(level.px_wid as f32, level.px_hei as f32),
+        );
+
+        let position = self.levels.len();
+        let (first, last) = (grid_cell(rect.min), grid_cell(rect.max));
+        for x in first.x..=last.x {
+            for y in first.y..=last.y {
+                self.grid
+                    .entry(IVec2::new(x, y))
+                    .or_default()
+                    .push(position);
+            }
+        }
+
+        self.by_iid.insert(level.iid.clone(), position);
+        self.levels.push(IndexedLevel {
+            iid: level.iid.clone(),
+            identifier: level.identifier.clone(),
+            uid: level.uid,
+            indices,
+            rect,
+        });
+    }
+
+    /// False until the project has loaded.
+    pub fn is_ready(&self) -> bool {
+        self.project.is_some()
+    }
+
+    pub fn levels(&self) -> &[IndexedLevel] {
+        &self.levels
+    }
+
+    pub fn get(&self, iid: &str) -> Option<&IndexedLevel> {
+        self.by_iid.get(iid).map(|position| &self.levels[*position])
+    }
+
+    pub fn selected(&self, selection: &LevelSelection) -> Option<&IndexedLevel> {
+        match selection {
+            LevelSelection::Iid(iid) => self.get(&iid.to_string()),
+            _ => self
+                .levels
+                .iter()
+                .find(|level| level.is_selected(selection)),
+        }
+    }
+
+    /// Levels whose bounds contain `point`, edges included.
+    pub fn at(&self, point: Vec2) -> impl Iterator<Item = &IndexedLevel> {
+        self.grid
+            .get(&grid_cell(point))
+            .into_iter()
+            .flatten()
+            .map(|position| &self.levels[*position])
+            .filter(move |level| level.rect.contains(point))
+    }
+
+    /// The level to select for a player at `point`, or `None` to keep
+    /// `selection`. The current level is kept until the player is
+    /// `SELECTION_HYSTERESIS` past its edge, and when several levels contain
+    /// the player the one it is deepest inside wins, ties going to the first
+    /// level in the project. Between levels or outside the world nothing changes.
+    pub fn next_selection(&self, selection: &LevelSelection, point: Vec2) -> Option<&IndexedLevel> {
+        if let Some(current) = self.selected(selection) {
+            if inset(current.rect, point) > -SELECTION_HYSTERESIS {
+                return None;
+            }
+        }
+
+        let mut best: Option<&IndexedLevel> = None;
+        for level in self.at(point) {
+            // `at` yields levels in project order, so only a deeper level replaces the best
+            if best.is_none_or(|best| inset(level.rect, point) > inset(best.rect, point)) {
+                best = Some(level);
+            }
+        }
+        best
+    }
+}
+
+fn grid_cell(point: Vec2) -> IVec2 {
+    (point / INDEX_CELL_SIZE).floor().as_ivec2()
+}
+
+fn index_levels(
+    mut index: ResMut<LevelIndex>,
+    mut asset_events: EventReader<AssetEvent<LdtkProject>>,
     ldtk_projects: Query<&LdtkProjectHandle>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
 ) {
-    for (level_iid, level_transform) in &level_query {
-        let ldtk_project = ldtk_project_assets
-            .get(ldtk_projects.single())
-            .expect("Project should be loaded if level has spawned");
-
-        let level = ldtk_project
-            .get_raw_level_by_iid(&level_iid.to_string())
-            .expect("Spawned level should exist in LDtk project");
-
-        let level_bounds = Rect {
-            min: Vec2::new(level_transform.translation.x, level_transform.translation.y),
-            max: Vec2::new(
-                level_transform.translation.x + level.px_wid as f32,
-                level_transform.translation.y + level.px_hei as f32,
-            ),
-        };
-
-        for player_transform in &player_query {
-            if player_transform.translation.x < level_bounds.max.x
-                && player_transform.translation.x > level_bounds.min.x
-                && player_transform.translation.y < level_bounds.max.y
-                && player_transform.translation.y > level_bounds.min.y
-                && !level_selection.is_match(&LevelIndices::default(), level)
-            {
-                *level_selection = LevelSelection::iid(level.iid.clone());
-                println!("Level changed to: {}", level.iid);
-            }
-        }
+    let Ok(handle) = ldtk_projects.get_single() else {
+        return;
+    };
+    let id = AssetId::<LdtkProject>::from(handle);
+
+    let reloaded = asset_events
+        .read()
+        .any(|event| event.is_modified(id) || event.is_loaded_with_dependencies(id));
+    if index.project == Some(id) && !reloaded {
+        return;
+    }
+
+    if let Some(project) = ldtk_project_assets.get(id) {
+        *index = LevelIndex::from_project(project.json_data());
+        index.project = Some(id);
+        debug!("Indexed {} levels", index.levels.len());
+    }
+}
+
+/// How far `point` is inside `rect`; negative when outside.
+fn inset(rect: Rect, point: Vec2) -> f32 {
+    let from_min = point - rect.min;
+    let from_max = rect.max - point;
+    from_min.min(from_max).min_element()
+}
+
+/// Selects the level around the player's collider.
+pub fn update_level_selection(
+    index: Res<LevelIndex>,
+    player_query: Query<(Entity, &GlobalTransform), With<Character>>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    let Some((_, player_transform)) = player_query.iter().min_by_key(|(entity, _)| *entity) else {
+        return;
+    };
+    let center = player_transform.translation().xy() + CAPSULE_CENTER;
+
+    if let Some(level) = index.next_selection(&level_selection, center) {
+        *level_selection = LevelSelection::iid(level.iid.clone());
+        println!("Level changed to: {}", level.iid);
+    }
+}
+
+fn send_level_transitions(
+    index: Res<LevelIndex>,
+    level_selection: Res<LevelSelection>,
+    mut entered: Local<Option<String>>,
+    mut entered_events: EventWriter<LevelEntered>,
+    mut exited_events: EventWriter<LevelExited>,
+) {
+    let Some(level) = index.selected(&level_selection) else {
+        return;
+    };
+    if entered.as_deref() == Some(level.iid.as_str()) {
+        return;
+    }
+
+    if let Some(previous) = entered.take() {
+        exited_events.send(LevelExited(LevelIid::new(previous)));
+    }
+    entered_events.send(LevelEntered(LevelIid::new(level.iid.clone())));
+    *entered = Some(level.iid.clone());
+}
+
+fn request_restart(input: Res<ButtonInput<KeyCode>>, mut restart: EventWriter<RestartRequested>) {
+    if input.just_pressed(KeyCode::KeyR) {
+        restart.send(RestartRequested);
+    }
+}
+
+fn restart_on_player_death(
+    mut died: EventReader<Died>,
+    players: Query<(), With<Character>>,
+    mut restart: EventWriter<RestartRequested>,
+) {
+    if died.read().any(|Died(entity)| players.contains(*entity)) {
+        info!("Player died");
+        restart.send(RestartRequested);
     }
 }
 
+/// Respawns the whole world, so worldly entities like the player are reset too.
+/// The player only spawns with its own level, which streaming may have dropped,
+/// so that level is selected again first.
 pub fn restart_level(
     mut commands: Commands,
-    level_query: Query<Entity, With<LevelIid>>,
-    input: Res<ButtonInput<KeyCode>>,
+    mut restart: EventReader<RestartRequested>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut world_query: Query<(Entity, &LdtkProjectHandle, &mut LevelSet)>,
 ) {
-    if input.just_pressed(KeyCode::KeyR) {
-        for level_entity in &level_query {
-            commands.entity(level_entity).insert(Respawn);
+    if restart.read().count() == 0 {
+        return;
+    }
+
+    for (world_entity, handle, mut level_set) in &mut world_query {
+        if let Some(iid) = ldtk_project_assets.get(handle).and_then(spawn_level_iid) {
+            *level_selection = LevelSelection::iid(iid);
+            level_set.iids.insert(LevelIid::new(iid));
         }
+        commands.entity(world_entity).insert(Respawn);
+    }
+}
+
+/// IID of the first level with a player in it.
+pub fn spawn_level_iid(project: &LdtkProject) -> Option<&str> {
+    project
+        .iter_raw_levels()
+        .find(|level| {
+            level
+                .layer_instances
+                .iter()
+                .flatten()
+                .flat_map(|layer| layer.entity_instances.iter())
+                .any(|entity| entity.identifier == registry::PLAYER)
+        })
+        .map(|level| level.iid.as_str())
+}
+
+/// Looks up the raw LDtk level matched by the current `LevelSelection`.
+#[derive(SystemParam)]
+pub struct CurrentLevel<'w, 's> {
+    ldtk_projects: Query<'w, 's, &'static LdtkProjectHandle>,
+    ldtk_project_assets: Res<'w, Assets<LdtkProject>>,
+    level_selection: Res<'w, LevelSelection>,
+}
+
+impl CurrentLevel<'_, '_> {
+    pub fn raw(&self) -> Option<&Level> {
+        let ldtk_project = self
+            .ldtk_project_assets
+            .get(self.ldtk_projects.get_single().ok()?)?;
+
+        ldtk_project
+            .iter_raw_levels_with_indices()
+            .find(|(indices, level)| self.level_selection.is_match(indices, level))
+            .map(|(_, level)| level)
+    }
+
+    pub fn identifier(&self) -> Option<&str> {
+        self.raw().map(|level| level.identifier.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A level in LDtk coordinates: y points down from the world origin.
+    fn level(identifier: &str, world_x: i32, world_y: i32, px_wid: i32, px_hei: i32) -> Level {
+        Level {
+            iid: identifier.to_lowercase(),
+            identifier: identifier.to_string(),
+            world_x,
+            world_y,
+            px_wid,
+            px_hei,
+            ..default()
+        }
+    }
+
+    /// `West` and `East` share the seam at x = 256; `Tall` overlaps `East`.
+    fn index() -> LevelIndex {
+        LevelIndex::from_project(&LdtkJson {
+            levels: vec![
+                level("West", 0, 0, 256, 256),
+                level("East", 256, 0, 256, 256),
+                level("Tall", 384, -256, 256, 512),
+            ],
+            ..default()
+        })
+    }
+
+    fn select(index: &LevelIndex, current: Option<&str>, point: Vec2) -> Option<String> {
+        // An IID matching no level stands in for no selection yet
+        let selection = LevelSelection::iid(current.unwrap_or("none"));
+        index
+            .next_selection(&selection, point)
+            .map(|level| level.iid.clone())
+    }
+
+    #[test]
+    fn levels_are_flipped_to_bevy_space() {
+        let index = index();
+
+        let west = index.get("west").unwrap();
+        assert_eq!(west.rect.min, Vec2::new(0.0, -256.0));
+        assert_eq!(west.rect.max, Vec2::new(256.0, 0.0));
+        assert_eq!(west.indices, LevelIndices::in_root(0));
+
+        let tall = index.get("tall").unwrap();
+        assert_eq!(tall.rect.min, Vec2::new(384.0, -256.0));
+        assert_eq!(tall.rect.max, Vec2::new(640.0, 256.0));
+    }
+
+    #[test]
+    fn points_on_a_seam_are_in_both_levels() {
+        let index = index();
+
+        let at: Vec<&str> = index
+            .at(Vec2::new(256.0, -128.0))
+            .map(|level| level.identifier.as_str())
+            .collect();
+
+        assert_eq!(at, vec!["West", "East"]);
+        assert_eq!(index.at(Vec2::new(700.0, -128.0)).count(), 0);
+    }
+
+    #[test]
+    fn inset_is_negative_outside() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 50.0);
+
+        assert_eq!(inset(rect, Vec2::new(50.0, 25.0)), 25.0);
+        assert_eq!(inset(rect, Vec2::new(10.0, 25.0)), 10.0);
+        assert_eq!(inset(rect, Vec2::new(100.0, 25.0)), 0.0);
+        assert_eq!(inset(rect, Vec2::new(108.0, 25.0)), -8.0);
+    }
+
+    #[test]
+    fn seam_keeps_the_current_level() {
+        let index = index();
+        let seam = Vec2::new(256.0, -128.0);
+
+        assert_eq!(select(&index, Some("west"), seam), None);
+        assert_eq!(select(&index, Some("east"), seam), None);
+        // Without a level yet the tie goes to the first one in the project
+        assert_eq!(select(&index, None, seam), Some("west".to_string()));
+    }
+
+    #[test]
+    fn selection_changes_once_past_the_hysteresis() {
+        let index = index();
+
+        assert_eq!(select(&index, Some("west"), Vec2::new(263.0, -128.0)), None);
+        assert_eq!(
+            select(&index, Some("west"), Vec2::new(264.0, -128.0)),
+            Some("east".to_string())
+        );
+    }
+
+    #[test]
+    fn straddling_point_picks_the_deepest_level() {
+        let index = index();
+
+        // 16 px into `Tall` but 112 px from the nearest edge of `East`
+        assert_eq!(
+            select(&index, None, Vec2::new(400.0, -128.0)),
+            Some("east".to_string())
+        );
+        assert_eq!(
+            select(&index, Some("west"), Vec2::new(500.0, -200.0)),
+            Some("tall".to_string())
+        );
     }
 }
@@ -0,0 +1,424 @@
+//! How the levels of an LDtk project connect: levels sharing an edge in the
+//! world layout, plus one-way `Teleport` destinations. `Player` entities mark
+//! where a run starts and `Exit` entities where it can end.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::{FieldValue, LdtkJson, Level};
+use serde::Serialize;
+
+use crate::registry;
+
+pub const TELEPORT: &str = "Teleport";
+pub const EXIT: &str = "Exit";
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// Levels touching along an edge; always added in both directions
+    Adjacent,
+    Teleport,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LevelNode {
+    pub iid: String,
+    pub identifier: String,
+    /// World bounds in LDtk pixels, y pointing down
+    pub world_x: i32,
+    pub world_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub spawn: bool,
+    pub exit: bool,
+}
+
+impl LevelNode {
+    fn from_level(level: &Level) -> Self {
+        let has_entity = |identifier: &str| {
+            level
+                .layer_instances
+                .iter()
+                .flatten()
+                .flat_map(|layer| layer.entity_instances.iter())
+                .any(|entity| entity.identifier == identifier)
+        };
+
+        LevelNode {
+            iid: level.iid.clone(),
+            identifier: level.identifier.clone(),
+            world_x: level.world_x,
+            world_y: level.world_y,
+            width: level.px_wid,
+            height: level.px_hei,
+            spawn: has_entity(registry::PLAYER),
+            exit: has_entity(EXIT),
+        }
+    }
+
+    /// Length of the edge shared with `other`, 0 when they only meet at a corner or not at all.
+    fn shared_edge(&self, other: &LevelNode) -> i32 {
+        let overlap = |a0: i32, a1: i32, b0: i32, b1: i32| (a1.min(b1) - a0.max(b0)).max(0);
+        let (right, bottom) = (self.world_x + self.width, self.world_y + self.height);
+        let (other_right, other_bottom) =
+            (other.world_x + other.width, other.world_y + other.height);
+
+        if right == other.world_x || other_right == self.world_x {
+            overlap(self.world_y, bottom, other.world_y, other_bottom)
+        } else if bottom == other.world_y || other_bottom == self.world_y {
+            overlap(self.world_x, right, other.world_x, other_right)
+        } else {
+            0
+        }
+    }
+
+    /// Distance between the centers of two levels in world pixels.
+    pub fn distance(&self, other: &LevelNode) -> f32 {
+        let center = |node: &LevelNode| {
+            Vec2::new(
+                node.world_x as f32 + node.width as f32 / 2.0,
+                node.world_y as f32 + node.height as f32 / 2.0,
+            )
+        };
+        center(self).distance(center(other))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct LevelLink {
+    pub from: usize,
+    pub to: usize,
+    pub kind: LinkKind,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LevelGraph {
+    pub levels: Vec<LevelNode>,
+    pub links: Vec<LevelLink>,
+}
+
+/// Problems found by `LevelGraph::analyze`, as level indices.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Reachability {
+    /// Levels no spawn leads to
+    pub unreachable: Vec<usize>,
+    /// Reachable levels from which neither a spawn nor an exit can be reached again
+    pub dead_ends: Vec<usize>,
+}
+
+impl LevelGraph {
+    pub fn from_project(project: &LdtkJson) -> Self {
+        let levels: Vec<&Level> = project
+            .levels
+            .iter()
+            .chain(project.worlds.iter().flat_map(|world| world.levels.iter()))
+            .collect();
+
+        let mut graph = LevelGraph::from_nodes(
+            levels
+                .iter()
+                .map(|level| LevelNode::from_level(level))
+                .collect(),
+        );
+
+        let index_by_iid: HashMap<&str, usize> = levels
+            .iter()
+            .enumerate()
+            .map(|(index, level)| (level.iid.as_str(), index))
+            .collect();
+
+        for (from, level) in levels.iter().enumerate() {
+            let teleports = level
+                .layer_instances
+                .iter()
+                .flatten()
+                .flat_map(|layer| layer.entity_instances.iter())
+                .filter(|entity| entity.identifier == TELEPORT)
+                .flat_map(|entity| entity.field_instances.iter());
+
+            for field in teleports {
+                if let FieldValue::EntityRef(Some(destination)) = &field.value {
+                    if let Some(&to) = index_by_iid.get(destination.level_iid.as_str()) {
+                        graph.links.push(LevelLink {
+                            from,
+                            to,
+                            kind: LinkKind::Teleport,
+                        });
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// Links every pair of levels sharing an edge.
+    pub fn from_nodes(levels: Vec<LevelNode>) -> Self {
+        let mut graph = LevelGraph {
+            levels,
+            links: Vec::new(),
+        };
+
+        for from in 0..graph.levels.len() {
+            for to in from + 1..graph.levels.len() {
+                if graph.levels[from].shared_edge(&graph.levels[to]) > 0 {
+                    graph.links.push(LevelLink {
+                        from,
+                        to,
+                        kind: LinkKind::Adjacent,
+                    });
+                    graph.links.push(LevelLink {
+                        from: to,
+                        to: from,
+                        kind: LinkKind::Adjacent,
+                    });
+                }
+            }
+        }
+
+        graph
+    }
+
+    pub fn index_of(&self, iid: &str) -> Option<usize> {
+        self.levels.iter().position(|level| level.iid == iid)
+    }
+
+    pub fn neighbours(&self, index: usize) -> impl Iterator<Item = &LevelLink> {
+        self.links.iter().filter(move |link| link.from == index)
+    }
+
+    /// Levels reachable from `starts`, following links forwards or, with
+    /// `reverse`, backwards.
+    fn walk(&self, starts: impl IntoIterator<Item = usize>, reverse: bool) -> HashSet<usize> {
+        let mut reached: HashSet<usize> = HashSet::new();
+        let mut queue = VecDeque::new();
+        for start in starts {
+            if reached.insert(start) {
+                queue.push_back(start);
+            }
+        }
+
+        while let Some(index) = queue.pop_front() {
+            for link in &self.links {
+                let (from, to) = if reverse {
+                    (link.to, link.from)
+                } else {
+                    (link.from, link.to)
+                };
+                if from == index && reached.insert(to) {
+                    queue.push_back(to);
+                }
+            }
+        }
+
+        reached
+    }
+
+    pub fn reachable_from(&self, starts: impl IntoIterator<Item = usize>) -> HashSet<usize> {
+        self.walk(starts, false)
+    }
+
+    /// Levels within `max_hops` adjacent levels of `start`, with their hop count.
+    /// Teleports are not followed.
+    pub fn hops_from(&self, start: usize, max_hops: u32) -> HashMap<usize, u32> {
+        let mut hops = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(index) = queue.pop_front() {
+            let next = hops[&index] + 1;
+            if next > max_hops {
+                continue;
+            }
+            for link in self.neighbours(index) {
+                if link.kind == LinkKind::Adjacent && !hops.contains_key(&link.to) {
+                    hops.insert(link.to, next);
+                    queue.push_back(link.to);
+                }
+            }
+        }
+
+        hops
+    }
+
+    pub fn analyze(&self) -> Reachability {
+        let spawns = (0..self.levels.len()).filter(|index| self.levels[*index].spawn);
+        let goals = (0..self.levels.len()).filter(|index| {
+            let level = &self.levels[*index];
+            level.spawn || level.exit
+        });
+
+        let reachable = self.walk(spawns, false);
+        let can_leave = self.walk(goals, true);
+
+        let mut report = Reachability::default();
+        for index in 0..self.levels.len() {
+            if !reachable.contains(&index) {
+                report.unreachable.push(index);
+            } else if !can_leave.contains(&index) {
+                report.dead_ends.push(index);
+            }
+        }
+        report
+    }
+
+    /// Graphviz source; adjacency is drawn as undirected edges, teleports as arrows.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph levels {\n");
+
+        for (index, level) in self.levels.iter().enumerate() {
+            let shape = match (level.spawn, level.exit) {
+                (true, _) => "doublecircle",
+                (_, true) => "doubleoctagon",
+                _ => "box",
+            };
+            dot.push_str(&format!(
+                "    {} [label=\"{}\", shape={}];\n",
+                index, level.identifier, shape
+            ));
+        }
+
+        for link in &self.links {
+            match link.kind {
+                LinkKind::Adjacent if link.from < link.to => {
+                    dot.push_str(&format!("    {} -> {} [dir=none];\n", link.from, link.to));
+                }
+                LinkKind::Adjacent => {}
+                LinkKind::Teleport => {
+                    dot.push_str(&format!(
+                        "    {} -> {} [style=dashed, label=\"teleport\"];\n",
+                        link.from, link.to
+                    ));
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Export<'a> {
+            #[serde(flatten)]
+            graph: &'a LevelGraph,
+            analysis: Reachability,
+        }
+
+        serde_json::to_string_pretty(&Export {
+            graph: self,
+            analysis: self.analyze(),
+        })
+        .expect("level graph is always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(identifier: &str, world_x: i32, world_y: i32, width: i32, height: i32) -> LevelNode {
+        LevelNode {
+            iid: identifier.to_lowercase(),
+            identifier: identifier.to_string(),
+            world_x,
+            world_y,
+            width,
+            height,
+            spawn: false,
+            exit: false,
+        }
+    }
+
+    fn teleport(graph: &mut LevelGraph, from: usize, to: usize) {
+        graph.links.push(LevelLink {
+            from,
+            to,
+            kind: LinkKind::Teleport,
+        });
+    }
+
+    /// Start -- Hall -- Vault in a row, Tower above Hall, Island off on its own.
+    fn graph() -> LevelGraph {
+        let mut start = node("Start", 0, 0, 256, 256);
+        start.spawn = true;
+        LevelGraph::from_nodes(vec![
+            start,
+            node("Hall", 256, 0, 512, 256),
+            node("Vault", 768, 0, 256, 256),
+            node("Tower", 512, -512, 256, 512),
+            node("Island", 2048, 2048, 256, 256),
+        ])
+    }
+
+    #[test]
+    fn only_shared_edges_link_levels() {
+        let graph = graph();
+
+        let neighbours = |index| {
+            let mut to: Vec<usize> = graph.neighbours(index).map(|link| link.to).collect();
+            to.sort();
+            to
+        };
+        assert_eq!(neighbours(0), vec![1]);
+        assert_eq!(neighbours(1), vec![0, 2, 3]);
+        // Tower only touches Vault at a corner
+        assert_eq!(neighbours(3), vec![1]);
+        assert_eq!(neighbours(4), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn unconnected_levels_are_unreachable() {
+        let graph = graph();
+
+        let analysis = graph.analyze();
+
+        assert_eq!(analysis.unreachable, vec![4]);
+        assert!(analysis.dead_ends.is_empty());
+        assert_eq!(graph.reachable_from([0]).len(), 4);
+    }
+
+    #[test]
+    fn one_way_teleports_make_dead_ends() {
+        let mut graph = graph();
+        teleport(&mut graph, 2, 4);
+
+        let analysis = graph.analyze();
+        assert!(analysis.unreachable.is_empty());
+        assert_eq!(analysis.dead_ends, vec![4]);
+
+        // An exit is a way out too
+        graph.levels[4].exit = true;
+        assert!(graph.analyze().dead_ends.is_empty());
+    }
+
+    #[test]
+    fn hops_count_adjacent_levels_only() {
+        let mut graph = graph();
+        teleport(&mut graph, 0, 4);
+
+        let hops = graph.hops_from(0, 5);
+
+        assert_eq!(hops, HashMap::from([(0, 0), (1, 1), (2, 2), (3, 2)]));
+    }
+
+    #[test]
+    fn hops_stop_at_the_limit() {
+        let graph = graph();
+
+        assert_eq!(graph.hops_from(2, 0), HashMap::from([(2, 0)]));
+        assert_eq!(graph.hops_from(2, 1), HashMap::from([(2, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn shipped_project_is_connected() {
+        let project: LdtkJson =
+            serde_json::from_str(include_str!("../assets/gridvania.ldtk")).unwrap();
+
+        let graph = LevelGraph::from_project(&project);
+        let analysis = graph.analyze();
+
+        assert!(graph.levels.iter().any(|level| level.spawn));
+        assert!(analysis.unreachable.is_empty());
+    }
+}
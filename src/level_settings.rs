@@ -0,0 +1,224 @@
+//! Per-level tweaks read from LDtk level fields: `music`, `ambientColor`,
+//! `gravityScale`, `cameraZoom` and `safeRoom`. Missing fields keep the
+//! defaults, and levels without an ambient color get the default clear color
+//! back; `Save` and `Shop` rooms are always safe. Spawned levels carry their
+//! settings as a component.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::{FieldValue, Level};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::camera::CAMERA_SCALE;
+use crate::character::controller_kinematic::GravityScale;
+use crate::character::Character;
+use crate::level::LevelEntered;
+use crate::save::SaveRequested;
+
+pub struct LevelSettingsPlugin;
+
+impl Plugin for LevelSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveLevelSettings>();
+        app.add_systems(
+            Update,
+            (
+                insert_level_settings,
+                apply_level_settings,
+                settle_new_players,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct LevelSettings {
+    /// Asset path of the track to play, `None` keeps whatever is playing
+    pub music: Option<String>,
+    pub ambient_color: Option<Color>,
+    pub gravity_scale: f32,
+    /// Above 1 zooms in
+    pub camera_zoom: f32,
+    /// Saves on entry, and the player can't be hurt inside
+    pub safe_room: bool,
+}
+
+impl Default for LevelSettings {
+    fn default() -> Self {
+        LevelSettings {
+            music: None,
+            ambient_color: None,
+            gravity_scale: 1.0,
+            camera_zoom: 1.0,
+            safe_room: false,
+        }
+    }
+}
+
+impl LevelSettings {
+    pub fn from_level(level: &Level) -> Self {
+        let defaults = LevelSettings::default();
+        let room_type = match level.get_field("roomType") {
+            Ok(FieldValue::Enum(Some(room_type))) => Some(room_type.as_str()),
+            _ => None,
+        };
+
+        LevelSettings {
+            music: level
+                .get_maybe_string_field("music")
+                .ok()
+                .cloned()
+                .flatten(),
+            ambient_color: level.get_color_field("ambientColor").ok().copied(),
+            gravity_scale: level
+                .get_float_field("gravityScale")
+                .copied()
+                .unwrap_or(defaults.gravity_scale),
+            camera_zoom: level
+                .get_float_field("cameraZoom")
+                .copied()
+                .ok()
+                .filter(|zoom| *zoom > 0.0)
+                .unwrap_or(defaults.camera_zoom),
+            safe_room: level.get_bool_field("safeRoom").copied().unwrap_or(false)
+                || matches!(room_type, Some("Save" | "Shop")),
+        }
+    }
+}
+
+/// Settings of the selected level.
+#[derive(Resource, Default, Debug)]
+pub struct ActiveLevelSettings(pub LevelSettings);
+
+/// Marks the player while it stands in a safe room.
+#[derive(Component, Debug)]
+pub struct InSafeRoom;
+
+fn raw_level<'a>(
+    ldtk_projects: &Query<&LdtkProjectHandle>,
+    ldtk_project_assets: &'a Assets<LdtkProject>,
+    iid: &LevelIid,
+) -> Option<&'a Level> {
+    ldtk_project_assets
+        .get(ldtk_projects.get_single().ok()?)?
+        .get_raw_level_by_iid(&iid.to_string())
+}
+
+fn insert_level_settings(
+    mut commands: Commands,
+    levels: Query<(Entity, &LevelIid), Added<LevelIid>>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    for (entity, iid) in levels.iter() {
+        if let Some(level) = raw_level(&ldtk_projects, &ldtk_project_assets, iid) {
+            commands
+                .entity(entity)
+                .insert(LevelSettings::from_level(level));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_level_settings(
+    mut commands: Commands,
+    mut entered: EventReader<LevelEntered>,
+    mut active: ResMut<ActiveLevelSettings>,
+    mut save_requested: EventWriter<SaveRequested>,
+    clear_color: Option<ResMut<ClearColor>>,
+    mut cameras: Query<&mut OrthographicProjection, With<Camera2d>>,
+    players: Query<Entity, With<Character>>,
+    levels: Query<(&LevelIid, &LevelSettings)>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    // Only the latest entry matters when several happen in one frame
+    let Some(LevelEntered(iid)) = entered.read().last() else {
+        return;
+    };
+
+    // The selection can move on before the level has spawned
+    let Some(settings) = levels
+        .iter()
+        .find(|(level_iid, _)| *level_iid == iid)
+        .map(|(_, settings)| settings.clone())
+        .or_else(|| {
+            raw_level(&ldtk_projects, &ldtk_project_assets, iid).map(LevelSettings::from_level)
+        })
+    else {
+        return;
+    };
+    debug!("Level settings for {}: {:?}", iid, settings);
+
+    if let Some(mut clear_color) = clear_color {
+        clear_color.0 = settings
+            .ambient_color
+            .unwrap_or_else(|| ClearColor::default().0);
+    }
+
+    // The camera clamp reads the scale back, so zooming in lets it get
+    // closer to the level edges
+    for mut projection in cameras.iter_mut() {
+        projection.scale = CAMERA_SCALE / settings.camera_zoom;
+    }
+
+    for player in players.iter() {
+        let mut player = commands.entity(player);
+        player.insert(GravityScale(settings.gravity_scale));
+        if settings.safe_room {
+            player.insert(InSafeRoom);
+        } else {
+            player.remove::<InSafeRoom>();
+        }
+    }
+
+    if settings.safe_room && !active.0.safe_room {
+        save_requested.send(SaveRequested);
+    }
+
+    active.0 = settings;
+}
+
+/// Players spawned after the level was entered still get its settings.
+fn settle_new_players(
+    mut commands: Commands,
+    active: Res<ActiveLevelSettings>,
+    players: Query<Entity, Added<Character>>,
+) {
+    for player in players.iter() {
+        let mut player = commands.entity(player);
+        player.insert(GravityScale(active.0.gravity_scale));
+        if active.0.safe_room {
+            player.insert(InSafeRoom);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_ldtk::ldtk::LdtkJson;
+
+    fn settings(identifier: &str) -> LevelSettings {
+        let project: LdtkJson =
+            serde_json::from_str(include_str!("../assets/gridvania.ldtk")).unwrap();
+        let level = project
+            .levels
+            .iter()
+            .find(|level| level.identifier == identifier)
+            .unwrap();
+        LevelSettings::from_level(level)
+    }
+
+    #[test]
+    fn missing_fields_keep_the_defaults() {
+        assert_eq!(settings("Cross_roads"), LevelSettings::default());
+    }
+
+    #[test]
+    fn fields_and_room_types_are_read() {
+        assert!(settings("Boss_room").ambient_color.is_some());
+        assert!(settings("SaveRoom").safe_room);
+        assert!(!settings("Entrance").safe_room);
+    }
+}
@@ -0,0 +1,225 @@
+//! Keeps the selected level and its GridVania neighbours spawned, so crossing
+//! into the next level never waits on it. Levels further than
+//! `LevelStreaming::unload_distance` adjacent levels away are despawned, and
+//! new levels are added to the `LevelSet` a few per frame.
+//!
+//! Streaming a level out despawns everything spawned from it. Its enemies come
+//! back at full health where LDtk places them, killed or not. Tile editor
+//! changes are replayed from `LevelEdits` when it streams back in, broken
+//! walls are kept in `SaveData`, and a boss lives outside its level behind a
+//! sealed arena, so none of those are lost.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::systems::{apply_level_selection, apply_level_set};
+
+use crate::level::CurrentLevel;
+use crate::level_graph::LevelGraph;
+
+pub struct LevelStreamingPlugin;
+
+impl Plugin for LevelStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelStreaming>();
+        app.init_resource::<StreamedLevels>();
+        // `apply_level_selection` resets the set to the selected level every
+        // frame, so the streamed set has to be written right after it
+        app.add_systems(
+            Update,
+            stream_levels
+                .after(apply_level_selection)
+                .before(apply_level_set),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct LevelStreaming {
+    /// Levels this many adjacent levels away from the selected one get spawned
+    pub preload_distance: u32,
+    /// Spawned levels further away than this are despawned; keeping it above
+    /// `preload_distance` avoids respawning levels when walking back and forth
+    pub unload_distance: u32,
+    /// How many levels may start spawning in the same frame
+    pub spawns_per_frame: usize,
+}
+
+impl Default for LevelStreaming {
+    fn default() -> Self {
+        LevelStreaming {
+            preload_distance: 1,
+            unload_distance: 2,
+            spawns_per_frame: 1,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct StreamedLevels {
+    graph: Option<(AssetId<LdtkProject>, LevelGraph)>,
+    /// Level IIDs currently in the `LevelSet`
+    spawned: HashSet<String>,
+}
+
+fn stream_levels(
+    streaming: Res<LevelStreaming>,
+    mut streamed: ResMut<StreamedLevels>,
+    current_level: CurrentLevel,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut worlds: Query<(&LdtkProjectHandle, &mut LevelSet)>,
+) {
+    let Ok((handle, mut level_set)) = worlds.get_single_mut() else {
+        return;
+    };
+    let Some(project) = ldtk_project_assets.get(handle) else {
+        return;
+    };
+    let Some(selected) = current_level.raw() else {
+        return;
+    };
+
+    let streamed = &mut *streamed;
+    let id = AssetId::<LdtkProject>::from(handle);
+    if streamed
+        .graph
+        .as_ref()
+        .is_none_or(|(graph_id, _)| *graph_id != id)
+    {
+        streamed.graph = Some((id, LevelGraph::from_project(project.json_data())));
+        streamed.spawned.clear();
+    }
+    let (_, graph) = streamed.graph.as_ref().unwrap();
+    let Some(start) = graph.index_of(&selected.iid) else {
+        return;
+    };
+
+    let spawned = next_spawned(graph, start, &streamed.spawned, &streaming);
+
+    for iid in spawned.difference(&streamed.spawned) {
+        debug!("Streaming in level {}", iid);
+    }
+    for iid in streamed.spawned.difference(&spawned) {
+        debug!("Streaming out level {}", iid);
+    }
+
+    let iids: HashSet<LevelIid> = spawned.iter().cloned().map(LevelIid::new).collect();
+    if level_set.iids != iids {
+        level_set.iids = iids;
+    }
+    streamed.spawned = spawned;
+}
+
+/// Level IIDs to keep spawned with `start` selected, given those spawned now.
+fn next_spawned(
+    graph: &LevelGraph,
+    start: usize,
+    spawned: &HashSet<String>,
+    streaming: &LevelStreaming,
+) -> HashSet<String> {
+    let max_distance = streaming.unload_distance.max(streaming.preload_distance);
+    let hops = graph.hops_from(start, max_distance);
+
+    // The selected level is never held back by the budget
+    let mut next: HashSet<String> = spawned
+        .iter()
+        .filter(|iid| {
+            graph
+                .index_of(iid)
+                .and_then(|index| hops.get(&index))
+                .is_some_and(|distance| *distance <= streaming.unload_distance)
+        })
+        .cloned()
+        .collect();
+    next.insert(graph.levels[start].iid.clone());
+
+    // Closest levels first, ties in project order so runs stay deterministic
+    let mut missing: Vec<(u32, usize)> = hops
+        .iter()
+        .filter(|(index, distance)| {
+            **distance <= streaming.preload_distance && !next.contains(&graph.levels[**index].iid)
+        })
+        .map(|(index, distance)| (*distance, *index))
+        .collect();
+    missing.sort_unstable();
+
+    for (_, index) in missing.into_iter().take(streaming.spawns_per_frame) {
+        next.insert(graph.levels[index].iid.clone());
+    }
+
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_graph::LevelNode;
+
+    /// Levels `a` to `e` side by side, each touching the next.
+    fn row() -> LevelGraph {
+        LevelGraph::from_nodes(
+            ["a", "b", "c", "d", "e"]
+                .iter()
+                .enumerate()
+                .map(|(index, iid)| LevelNode {
+                    iid: iid.to_string(),
+                    identifier: iid.to_uppercase(),
+                    world_x: index as i32 * 256,
+                    world_y: 0,
+                    width: 256,
+                    height: 256,
+                    spawn: false,
+                    exit: false,
+                })
+                .collect(),
+        )
+    }
+
+    fn iids(iids: &[&str]) -> HashSet<String> {
+        iids.iter().map(|iid| iid.to_string()).collect()
+    }
+
+    #[test]
+    fn neighbours_stream_in_within_the_budget() {
+        let graph = row();
+        let streaming = LevelStreaming::default();
+
+        let first = next_spawned(&graph, 2, &HashSet::new(), &streaming);
+        assert_eq!(first, iids(&["c", "b"]));
+
+        let second = next_spawned(&graph, 2, &first, &streaming);
+        assert_eq!(second, iids(&["b", "c", "d"]));
+        assert_eq!(next_spawned(&graph, 2, &second, &streaming), second);
+    }
+
+    #[test]
+    fn levels_past_the_unload_distance_stream_out() {
+        let graph = row();
+        let streaming = LevelStreaming::default();
+        let spawned = iids(&["a", "b", "c"]);
+
+        // Two levels away from `c` is still inside the unload distance
+        let next = next_spawned(&graph, 2, &spawned, &streaming);
+        assert!(next.contains("a"));
+
+        let next = next_spawned(&graph, 3, &next, &streaming);
+        assert!(!next.contains("a"));
+        assert!(next.contains("b"));
+        assert!(next.contains("d"));
+    }
+
+    #[test]
+    fn selected_level_ignores_the_budget() {
+        let graph = row();
+        let streaming = LevelStreaming {
+            spawns_per_frame: 0,
+            ..default()
+        };
+
+        assert_eq!(
+            next_spawned(&graph, 4, &HashSet::new(), &streaming),
+            iids(&["e"])
+        );
+    }
+}
@@ -0,0 +1,136 @@
+pub mod audio;
+pub mod boss;
+pub mod breakable;
+pub mod camera;
+pub mod character;
+pub mod combat;
+pub mod cursor_tracking;
+pub mod decoration;
+pub mod enemy;
+pub mod health;
+pub mod item;
+pub mod json_asset;
+pub mod keyboard_rotation;
+pub mod ldtk_export;
+pub mod level;
+pub mod level_graph;
+pub mod level_settings;
+pub mod level_streaming;
+pub mod loading;
+pub mod navigation;
+pub mod physics;
+pub mod registry;
+pub mod replay;
+pub mod save;
+pub mod tile_grid;
+pub mod time_trial;
+
+use audio::GameAudioPlugin;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use boss::BossPlugin;
+use breakable::BreakablePlugin;
+use camera::CameraPlugin;
+use character::animation::CharacterAnimationPlugin;
+use character::controller_kinematic::KinematicControllerPlugin;
+use character::input::PlayerInputPlugin;
+use character::Character;
+use combat::CombatPlugin;
+use decoration::DecorationPlugin;
+use enemy::EnemyPlugin;
+use health::{Health, HealthPlugin};
+use item::ItemPlugin;
+use keyboard_rotation::KeyboardRotationPlugin;
+use level::LevelPlugin;
+use level_settings::LevelSettingsPlugin;
+use level_streaming::LevelStreamingPlugin;
+use navigation::NavigationPlugin;
+use physics::{layers, BodyKind};
+use replay::ReplayPlugin;
+use save::SavePlugin;
+use time_trial::TimeTrialPlugin;
+
+/// Everything needed to play a level, without windowing, audio or physics
+/// backend setup. Expects `LdtkPlugin` and `PhysicsPlugin` to be added too.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(LevelPlugin)
+            .add_plugins(LevelStreamingPlugin)
+            .add_plugins(LevelSettingsPlugin)
+            .add_plugins(GameAudioPlugin)
+            .add_plugins(PlayerInputPlugin)
+            .add_plugins(KinematicControllerPlugin)
+            .add_plugins(CharacterAnimationPlugin)
+            .add_plugins(CameraPlugin)
+            .add_plugins(KeyboardRotationPlugin)
+            .add_plugins(ReplayPlugin)
+            .add_plugins(TimeTrialPlugin)
+            .add_plugins(HealthPlugin)
+            .add_plugins(CombatPlugin)
+            .add_plugins(EnemyPlugin)
+            .add_plugins(SavePlugin)
+            .add_plugins(BossPlugin)
+            .add_plugins(NavigationPlugin)
+            .add_plugins(BreakablePlugin)
+            .add_plugins(ItemPlugin)
+            .add_plugins(DecorationPlugin)
+            .register_ldtk_entity::<PlayerBundle>(registry::PLAYER)
+            .register_ldtk_int_cell::<WallBundle>(registry::WALL)
+            .register_ldtk_int_cell::<PlatformBundle>(registry::PLATFORM)
+            .add_systems(Update, spawn_wall_collision);
+    }
+}
+
+#[derive(Default, Bundle, LdtkEntity)]
+pub struct PlayerBundle {
+    player: Character,
+    #[sprite_sheet]
+    sprite_sheet: Sprite,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(player_health)]
+    health: Health,
+    // Kept by the world, not the level it starts in, which may be streamed out
+    #[worldly]
+    worldly: Worldly,
+}
+
+fn player_health(entity_instance: &EntityInstance) -> Health {
+    Health::from_hp_field(entity_instance).with_invulnerability(1.0)
+}
+
+/// Gives every wall tile a collider child, so removing the tile removes it too.
+pub fn spawn_wall_collision(
+    mut commands: Commands,
+    wall_query: Query<(Entity, &Transform), (Added<Wall>, With<GridCoords>)>,
+) {
+    for (entity, transform) in wall_query.iter() {
+        println!("Spawned wall at {:?}", transform);
+        commands.entity(entity).with_child((
+            // Tiles are already placed at the center of their cell
+            Transform::default(),
+            physics::cuboid(8.0, 8.0),
+            physics::body(BodyKind::Fixed),
+            physics::friction(0.0),
+            physics::collision_layers(layers::WORLD, layers::ALL),
+        ));
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Wall;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct WallBundle {
+    wall: Wall,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Platform;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct PlatformBundle {
+    platform: Platform,
+}
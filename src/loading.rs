@@ -0,0 +1,165 @@
+//! Startup asset loading: a progress bar while `bevy_asset_loader` works
+//! through the collections, and a screen listing the assets that failed with
+//! Enter to try them again. The game world is only spawned once loading is done.
+
+use std::collections::BTreeSet;
+
+use bevy::asset::UntypedAssetLoadFailedEvent;
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use iyes_progress::{ProgressPlugin, ProgressTracker};
+
+use crate::decoration::DecorationAssets;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<MyStates>()
+            .enable_state_scoped_entities::<MyStates>()
+            .init_resource::<FailedAssets>()
+            .add_plugins(
+                ProgressPlugin::<MyStates>::new()
+                    .with_state_transition(MyStates::AssetLoading, MyStates::Next),
+            )
+            .add_loading_state(
+                LoadingState::new(MyStates::AssetLoading)
+                    .on_failure_continue_to_state(MyStates::LoadingFailed)
+                    .load_collection::<DecorationAssets>(),
+            )
+            .add_systems(OnEnter(MyStates::AssetLoading), spawn_loading_screen)
+            .add_systems(
+                Update,
+                (collect_failures, update_loading_screen).run_if(in_state(MyStates::AssetLoading)),
+            )
+            .add_systems(OnEnter(MyStates::LoadingFailed), spawn_failure_screen)
+            .add_systems(
+                Update,
+                retry_failed.run_if(in_state(MyStates::LoadingFailed)),
+            );
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum MyStates {
+    #[default]
+    AssetLoading,
+    Next,
+    /// Some asset could not be loaded; lists them and waits for a retry
+    LoadingFailed,
+}
+
+/// Paths of the assets that failed during the current loading attempt.
+#[derive(Resource, Default, Debug)]
+pub struct FailedAssets(pub BTreeSet<String>);
+
+#[derive(Component)]
+struct LoadingBar;
+
+#[derive(Component)]
+struct LoadingText;
+
+fn screen_root() -> impl Bundle {
+    (
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(12.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.08, 0.08, 0.1)),
+    )
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((screen_root(), StateScoped(MyStates::AssetLoading)))
+        .with_children(|screen| {
+            screen.spawn((Text::new("Loading"), LoadingText));
+            screen
+                .spawn((
+                    Node {
+                        width: Val::Px(300.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.25, 0.25, 0.3)),
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.45, 0.75, 0.4)),
+                    LoadingBar,
+                ));
+        });
+}
+
+fn update_loading_screen(
+    progress: Res<ProgressTracker<MyStates>>,
+    mut bars: Query<&mut Node, With<LoadingBar>>,
+    mut texts: Query<&mut Text, With<LoadingText>>,
+) {
+    let progress = progress.get_global_progress();
+    let fraction = if progress.total == 0 {
+        0.0
+    } else {
+        progress.done as f32 / progress.total as f32
+    };
+
+    for mut bar in bars.iter_mut() {
+        bar.width = Val::Percent(fraction * 100.0);
+    }
+    for mut text in texts.iter_mut() {
+        text.0 = format!("Loading {}/{}", progress.done, progress.total);
+    }
+}
+
+fn collect_failures(
+    mut failures: EventReader<UntypedAssetLoadFailedEvent>,
+    mut failed: ResMut<FailedAssets>,
+) {
+    for failure in failures.read() {
+        error!("Could not load {}: {}", failure.path, failure.error);
+        failed.0.insert(failure.path.to_string());
+    }
+}
+
+fn spawn_failure_screen(mut commands: Commands, failed: Res<FailedAssets>) {
+    commands
+        .spawn((screen_root(), StateScoped(MyStates::LoadingFailed)))
+        .with_children(|screen| {
+            screen.spawn(Text::new("Some assets could not be loaded:"));
+            for path in failed.0.iter() {
+                screen.spawn((
+                    Text::new(path.clone()),
+                    TextColor(Color::srgb(0.9, 0.4, 0.4)),
+                ));
+            }
+            screen.spawn(Text::new("Press Enter to retry"));
+        });
+}
+
+fn retry_failed(
+    keys: Res<ButtonInput<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    mut failed: ResMut<FailedAssets>,
+    mut next_state: ResMut<NextState<MyStates>>,
+) {
+    // Not R, which restarts the level
+    if !keys.just_pressed(KeyCode::Enter) {
+        return;
+    }
+
+    // Failed handles are kept by the asset server, so loading them again needs a reload
+    for path in std::mem::take(&mut failed.0) {
+        info!("Retrying {}", path);
+        asset_server.reload(path);
+    }
+    next_state.set(MyStates::AssetLoading);
+}
@@ -0,0 +1,544 @@
+//! Platformer navigation graphs built from the `Collisions` IntGrid layer of
+//! each spawned level, with an A* query that respects a jump height.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::ldtk::Level;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::registry;
+
+pub const COLLISIONS_LAYER: &str = "Collisions";
+// Jumps are only linked up to this far; queries can restrict them further
+const MAX_JUMP_HEIGHT: i32 = 6;
+const MAX_JUMP_ACROSS: i32 = 4;
+const FALL_COST_PER_CELL: f32 = 0.5;
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGraphs>();
+        app.add_systems(Update, build_nav_graphs);
+    }
+}
+
+/// IntGrid values that block movement: walls and breakable walls.
+pub fn is_solid(value: i32) -> bool {
+    value == registry::WALL || value == registry::BREAKABLE
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LinkKind {
+    Walk,
+    /// Needs a jump reaching `height` cells above the start
+    Jump {
+        height: i32,
+    },
+    Fall,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct NavLink {
+    pub to: usize,
+    pub kind: LinkKind,
+    pub cost: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Waypoint {
+    pub coords: GridCoords,
+    /// How this waypoint is reached from the previous one
+    pub kind: LinkKind,
+}
+
+/// Walkable cells of one level (empty cells standing on something solid) and
+/// the links between them. Coordinates follow `GridCoords`, origin bottom-left.
+#[derive(Debug, Default)]
+pub struct NavGraph {
+    pub width: i32,
+    pub height: i32,
+    pub grid_size: i32,
+    cells: Vec<i32>,
+    nodes: Vec<GridCoords>,
+    node_index: HashMap<GridCoords, usize>,
+    links: Vec<Vec<NavLink>>,
+}
+
+impl NavGraph {
+    /// Builds the graph from the `Collisions` layer of `level`, if it has one.
+    pub fn from_level(level: &Level) -> Option<Self> {
+        let layer = level
+            .layer_instances
+            .as_ref()?
+            .iter()
+            .find(|layer| layer.identifier == COLLISIONS_LAYER)?;
+
+        Some(NavGraph::from_int_grid(
+            layer.c_wid,
+            layer.c_hei,
+            layer.grid_size,
+            &layer.int_grid_csv,
+        ))
+    }
+
+    /// `csv` is in LDtk order: rows from the top, left to right. Values past
+    /// `width * height` are ignored.
+    pub fn from_int_grid(width: i32, height: i32, grid_size: i32, csv: &[i32]) -> Self {
+        let width = width.max(0);
+        let height = height.max(0);
+        let mut cells = vec![0; (width * height) as usize];
+        for (index, value) in csv.iter().take(cells.len()).enumerate() {
+            let x = index as i32 % width;
+            let y = height - 1 - index as i32 / width;
+            cells[(y * width + x) as usize] = *value;
+        }
+
+        let mut graph = NavGraph {
+            width,
+            height,
+            grid_size,
+            cells,
+            ..default()
+        };
+        graph.build();
+        graph
+    }
+
+    pub fn value(&self, coords: GridCoords) -> i32 {
+        if coords.x < 0 || coords.y < 0 || coords.x >= self.width || coords.y >= self.height {
+            // Outside the level counts as solid so paths never leave it
+            return registry::WALL;
+        }
+        self.cells[(coords.y * self.width + coords.x) as usize]
+    }
+
+    pub fn set_value(&mut self, coords: GridCoords, value: i32) {
+        if coords.x >= 0 && coords.y >= 0 && coords.x < self.width && coords.y < self.height {
+            self.cells[(coords.y * self.width + coords.x) as usize] = value;
+            self.build();
+        }
+    }
+
+    fn is_open(&self, coords: GridCoords) -> bool {
+        !is_solid(self.value(coords))
+    }
+
+    pub fn is_walkable(&self, coords: GridCoords) -> bool {
+        self.is_open(coords) && !self.is_open(GridCoords::new(coords.x, coords.y - 1))
+    }
+
+    /// Center of a cell relative to the level's bottom-left corner.
+    pub fn cell_center(&self, coords: GridCoords) -> Vec2 {
+        (Vec2::new(coords.x as f32, coords.y as f32) + 0.5) * self.grid_size as f32
+    }
+
+    pub fn coords_at(&self, local: Vec2) -> GridCoords {
+        let cell = (local / self.grid_size as f32).floor();
+        GridCoords::new(cell.x as i32, cell.y as i32)
+    }
+
+    /// First walkable cell at or below `coords`.
+    pub fn ground_below(&self, coords: GridCoords) -> Option<GridCoords> {
+        (0..=coords.y)
+            .rev()
+            .map(|y| GridCoords::new(coords.x, y))
+            .take_while(|cell| self.is_open(*cell))
+            .find(|cell| self.is_walkable(*cell))
+    }
+
+    fn build(&mut self) {
+        self.nodes = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| GridCoords::new(x, y)))
+            .filter(|coords| self.is_walkable(*coords))
+            .collect();
+        self.node_index = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, coords)| (*coords, index))
+            .collect();
+        self.links = self
+            .nodes
+            .iter()
+            .map(|coords| self.links_from(*coords))
+            .collect();
+    }
+
+    fn links_from(&self, from: GridCoords) -> Vec<NavLink> {
+        let mut links = Vec::new();
+
+        for dx in [-1, 1] {
+            let side = GridCoords::new(from.x + dx, from.y);
+
+            if let Some(&to) = self.node_index.get(&side) {
+                links.push(NavLink {
+                    to,
+                    kind: LinkKind::Walk,
+                    cost: 1.0,
+                });
+            } else if self.is_open(side) {
+                // Step off the ledge and drop to whatever is below
+                if let Some(landing) = self.ground_below(side) {
+                    let drop = (from.y - landing.y) as f32;
+                    links.push(NavLink {
+                        to: self.node_index[&landing],
+                        kind: LinkKind::Fall,
+                        cost: 1.0 + drop * FALL_COST_PER_CELL,
+                    });
+                }
+            }
+        }
+
+        for dx in -MAX_JUMP_ACROSS..=MAX_JUMP_ACROSS {
+            for dy in 0..=MAX_JUMP_HEIGHT {
+                if dx.abs() <= 1 && dy == 0 {
+                    continue;
+                }
+
+                let target = GridCoords::new(from.x + dx, from.y + dy);
+                let Some(&to) = self.node_index.get(&target) else {
+                    continue;
+                };
+
+                // Need one cell of clearance over the higher of the two ends
+                let height = dy + 1;
+                if self.jump_is_clear(from, target, height) {
+                    links.push(NavLink {
+                        to,
+                        kind: LinkKind::Jump { height },
+                        cost: (dx.abs() + dy) as f32 + 1.0,
+                    });
+                }
+            }
+        }
+
+        links
+    }
+
+    /// Straight up from `from`, across at the apex, then down onto `to`.
+    fn jump_is_clear(&self, from: GridCoords, to: GridCoords, height: i32) -> bool {
+        let apex = from.y + height;
+        let rises = (from.y + 1..=apex).all(|y| self.is_open(GridCoords::new(from.x, y)));
+        let step = (to.x - from.x).signum();
+        let across = (1..=(to.x - from.x).abs())
+            .all(|i| self.is_open(GridCoords::new(from.x + i * step, apex)));
+        let falls = (to.y..apex).all(|y| self.is_open(GridCoords::new(to.x, y)));
+
+        rises && across && falls
+    }
+
+    /// A* from the ground under `start` to the ground under `goal`, using
+    /// only jumps no higher than `jump_height` cells.
+    pub fn find_path(
+        &self,
+        start: GridCoords,
+        goal: GridCoords,
+        jump_height: i32,
+    ) -> Option<Vec<Waypoint>> {
+        let start = *self.node_index.get(&self.ground_below(start)?)?;
+        let goal = *self.node_index.get(&self.ground_below(goal)?)?;
+
+        // Every link pays at least 1 per column crossed, 1 per cell climbed
+        // and FALL_COST_PER_CELL per cell dropped, so this never overestimates
+        let heuristic = |node: usize| {
+            let (a, b) = (self.nodes[node], self.nodes[goal]);
+            let across = (a.x - b.x).abs() as f32;
+            let vertical = if a.y > b.y {
+                (a.y - b.y) as f32 * FALL_COST_PER_CELL
+            } else {
+                (b.y - a.y) as f32
+            };
+            across + vertical
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut cost = HashMap::from([(start, 0.0)]);
+        let mut came_from: HashMap<usize, (usize, LinkKind)> = HashMap::new();
+        open.push(OpenNode {
+            node: start,
+            priority: heuristic(start),
+        });
+
+        while let Some(OpenNode { node, .. }) = open.pop() {
+            if node == goal {
+                let mut path = vec![];
+                let mut current = goal;
+                while let Some(&(previous, kind)) = came_from.get(&current) {
+                    path.push(Waypoint {
+                        coords: self.nodes[current],
+                        kind,
+                    });
+                    current = previous;
+                }
+                path.push(Waypoint {
+                    coords: self.nodes[start],
+                    kind: LinkKind::Walk,
+                });
+                path.reverse();
+                return Some(path);
+            }
+
+            for link in &self.links[node] {
+                if let LinkKind::Jump { height } = link.kind {
+                    if height > jump_height {
+                        continue;
+                    }
+                }
+
+                let next_cost = cost[&node] + link.cost;
+                if cost.get(&link.to).is_none_or(|known| next_cost < *known) {
+                    cost.insert(link.to, next_cost);
+                    came_from.insert(link.to, (node, link.kind));
+                    open.push(OpenNode {
+                        node: link.to,
+                        priority: next_cost + heuristic(link.to),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+struct OpenNode {
+    node: usize,
+    priority: f32,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    // Reversed so the heap pops the lowest priority first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+/// Navigation graphs of every level spawned so far, keyed by level IID.
+#[derive(Resource, Default)]
+pub struct NavGraphs(HashMap<String, NavGraph>);
+
+impl NavGraphs {
+    pub fn get(&self, level_iid: &LevelIid) -> Option<&NavGraph> {
+        self.0.get(level_iid.as_str())
+    }
+
+    pub fn get_mut(&mut self, level_iid: &LevelIid) -> Option<&mut NavGraph> {
+        self.0.get_mut(level_iid.as_str())
+    }
+
+    pub fn insert(&mut self, level_iid: LevelIid, graph: NavGraph) {
+        self.0.insert(level_iid.to_string(), graph);
+    }
+}
+
+pub(crate) fn build_nav_graphs(
+    mut nav_graphs: ResMut<NavGraphs>,
+    levels: Query<&LevelIid, Added<LevelIid>>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    for level_iid in &levels {
+        if nav_graphs.0.contains_key(level_iid.as_str()) {
+            continue;
+        }
+
+        let Some(ldtk_project) = ldtk_projects
+            .get_single()
+            .ok()
+            .and_then(|handle| ldtk_project_assets.get(handle))
+        else {
+            continue;
+        };
+
+        let Some(graph) = ldtk_project
+            .get_raw_level_by_iid(&level_iid.to_string())
+            .and_then(NavGraph::from_level)
+        else {
+            continue;
+        };
+
+        debug!(
+            "Built navigation graph for {} ({} nodes)",
+            level_iid,
+            graph.nodes.len()
+        );
+        nav_graphs.insert(level_iid.clone(), graph);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a graph from rows drawn top to bottom, one per line, `#` being
+    /// a wall.
+    fn graph(rows: &str) -> NavGraph {
+        let rows: Vec<&str> = rows
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect();
+        let width = rows[0].len() as i32;
+        let csv: Vec<i32> = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| if c == '#' { registry::WALL } else { 0 })
+            .collect();
+        NavGraph::from_int_grid(width, rows.len() as i32, 16, &csv)
+    }
+
+    fn coords(path: &[Waypoint]) -> Vec<GridCoords> {
+        path.iter().map(|waypoint| waypoint.coords).collect()
+    }
+
+    #[test]
+    fn walks_along_flat_ground() {
+        let graph = graph(
+            "
+            ......
+            ......
+            ######",
+        );
+
+        let path = graph
+            .find_path(GridCoords::new(0, 1), GridCoords::new(5, 1), 0)
+            .unwrap();
+
+        assert_eq!(path.len(), 6);
+        assert!(path.iter().all(|waypoint| waypoint.kind == LinkKind::Walk));
+    }
+
+    #[test]
+    fn start_and_goal_snap_to_the_ground() {
+        let graph = graph(
+            "
+            ......
+            ......
+            ######",
+        );
+
+        let path = graph
+            .find_path(GridCoords::new(0, 2), GridCoords::new(2, 2), 0)
+            .unwrap();
+
+        assert_eq!(
+            coords(&path),
+            vec![
+                GridCoords::new(0, 1),
+                GridCoords::new(1, 1),
+                GridCoords::new(2, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps_respect_the_jump_height() {
+        let graph = graph(
+            "
+            ......
+            ......
+            ...###
+            ...###
+            ######",
+        );
+        let start = GridCoords::new(0, 1);
+        let goal = GridCoords::new(5, 3);
+
+        assert!(graph.find_path(start, goal, 2).is_none());
+
+        let path = graph.find_path(start, goal, 3).unwrap();
+        assert!(path
+            .iter()
+            .any(|waypoint| waypoint.kind == LinkKind::Jump { height: 3 }));
+        assert_eq!(path.last().unwrap().coords, goal);
+    }
+
+    #[test]
+    fn drops_off_ledges() {
+        let graph = graph(
+            "
+            ......
+            ###...
+            ......
+            ......
+            ######",
+        );
+
+        let path = graph
+            .find_path(GridCoords::new(0, 3), GridCoords::new(5, 1), 0)
+            .unwrap();
+
+        assert!(path.iter().any(|waypoint| waypoint.kind == LinkKind::Fall));
+        assert_eq!(path.last().unwrap().coords, GridCoords::new(5, 1));
+    }
+
+    #[test]
+    fn takes_the_cheaper_of_a_drop_and_a_staircase() {
+        // Dropping 4 cells through the gap costs 3, far less than walking
+        // over to the steps on the right
+        let graph = graph(
+            "
+            ..........
+            ##.#####..
+            ........#.
+            ........##
+            ..........
+            ##########",
+        );
+
+        let path = graph
+            .find_path(GridCoords::new(0, 5), GridCoords::new(0, 1), 0)
+            .unwrap();
+
+        assert_eq!(
+            coords(&path),
+            vec![
+                GridCoords::new(0, 5),
+                GridCoords::new(1, 5),
+                GridCoords::new(2, 1),
+                GridCoords::new(1, 1),
+                GridCoords::new(0, 1)
+            ]
+        );
+        assert_eq!(path[2].kind, LinkKind::Fall);
+    }
+
+    #[test]
+    fn unreachable_goal_has_no_path() {
+        let graph = graph(
+            "
+            ...#..
+            ...#..
+            ######",
+        );
+
+        assert!(graph
+            .find_path(GridCoords::new(0, 1), GridCoords::new(5, 1), 0)
+            .is_none());
+    }
+
+    #[test]
+    fn extra_csv_values_are_ignored() {
+        let graph = NavGraph::from_int_grid(2, 2, 16, &[0, 0, 1, 1, 1, 1, 1]);
+
+        assert!(graph.is_walkable(GridCoords::new(0, 1)));
+        assert_eq!(graph.value(GridCoords::new(1, 0)), registry::WALL);
+    }
+}
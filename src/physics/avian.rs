@@ -0,0 +1,249 @@
+use avian2d::prelude as avian;
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use super::{
+    BodyKind, CollisionEnded, CollisionStarted, KinematicMover, KinematicMoverOutput,
+    PhysicsPlugin, RayFilter, RayHit,
+};
+
+pub use avian2d::prelude::{Collider, ColliderDisabled, RigidBody};
+
+// Gap kept between a kinematic mover and whatever it slides against
+const SKIN_WIDTH: f32 = 0.5;
+const MAX_SLIDES: usize = 4;
+// Enough to see past the sensors overlapping a mover's path
+const MAX_SHAPE_HITS: u32 = 8;
+
+pub(super) fn build_backend(app: &mut App, plugin: &PhysicsPlugin) {
+    app.add_plugins(avian::PhysicsPlugins::default().with_length_unit(plugin.pixels_per_meter));
+    // Match rapier, which scales the default gravity by pixels per meter
+    app.insert_resource(avian::Gravity(Vec2::NEG_Y * 9.81 * plugin.pixels_per_meter));
+
+    if plugin.debug_render {
+        app.add_plugins(avian::PhysicsDebugPlugin::default());
+    }
+
+    app.add_systems(
+        FixedPostUpdate,
+        move_kinematic_movers.before(avian::PhysicsSet::Prepare),
+    );
+    app.add_systems(
+        FixedPostUpdate,
+        forward_collision_events.after(avian::PhysicsSet::Sync),
+    );
+}
+
+pub fn body(kind: BodyKind) -> RigidBody {
+    match kind {
+        BodyKind::Dynamic => RigidBody::Dynamic,
+        BodyKind::Fixed => RigidBody::Static,
+        BodyKind::Kinematic => RigidBody::Kinematic,
+    }
+}
+
+pub fn cuboid(half_x: f32, half_y: f32) -> Collider {
+    Collider::rectangle(half_x * 2.0, half_y * 2.0)
+}
+
+pub fn capsule(start: Vec2, end: Vec2, radius: f32) -> Collider {
+    Collider::capsule_endpoints(radius, start, end)
+}
+
+pub fn ball(radius: f32) -> Collider {
+    Collider::circle(radius)
+}
+
+pub fn sensor() -> impl Bundle {
+    avian::Sensor
+}
+
+pub fn friction(coefficient: f32) -> impl Bundle {
+    avian::Friction::new(coefficient)
+}
+
+pub fn lock_rotation() -> impl Bundle {
+    avian::LockedAxes::ROTATION_LOCKED
+}
+
+pub fn damping(linear: f32, angular: f32) -> impl Bundle {
+    (avian::LinearDamping(linear), avian::AngularDamping(angular))
+}
+
+pub fn gravity_scale(scale: f32) -> impl Bundle {
+    avian::GravityScale(scale)
+}
+
+pub fn continuous_collision() -> impl Bundle {
+    avian::SweptCcd::default()
+}
+
+/// Avian reports every contact, so there is nothing to opt into.
+pub fn report_collisions() -> impl Bundle {}
+
+/// Only collide with colliders whose memberships intersect `filters`, and
+/// the other way around.
+pub fn collision_layers(memberships: u32, filters: u32) -> impl Bundle {
+    avian::CollisionLayers::new(avian::LayerMask(memberships), avian::LayerMask(filters))
+}
+
+pub fn velocity() -> impl Bundle {
+    (avian::LinearVelocity::ZERO, avian::AngularVelocity::ZERO)
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct BodyVelocity {
+    linear: &'static mut avian::LinearVelocity,
+    angular: &'static mut avian::AngularVelocity,
+}
+
+impl BodyVelocityItem<'_> {
+    pub fn linear(&self) -> Vec2 {
+        self.linear.0
+    }
+
+    pub fn set_linear(&mut self, linear: Vec2) {
+        self.linear.0 = linear;
+    }
+
+    pub fn angular(&self) -> f32 {
+        self.angular.0
+    }
+
+    pub fn set_angular(&mut self, angular: f32) {
+        self.angular.0 = angular;
+    }
+}
+
+#[derive(SystemParam)]
+pub struct PhysicsQuery<'w, 's> {
+    spatial: avian::SpatialQuery<'w, 's>,
+    sensors: Query<'w, 's, (), With<avian::Sensor>>,
+}
+
+impl PhysicsQuery<'_, '_> {
+    pub fn cast_ray(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &RayFilter,
+    ) -> Option<RayHit> {
+        let direction = Dir2::new(direction).ok()?;
+        let query_filter = avian::SpatialQueryFilter::from_mask(avian::LayerMask(filter.mask))
+            .with_excluded_entities(filter.exclude);
+
+        self.spatial
+            .ray_hits(origin, direction, max_distance, 8, true, &query_filter)
+            .into_iter()
+            .filter(|hit| filter.include_sensors || !self.sensors.contains(hit.entity))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+            .map(|hit| RayHit {
+                entity: hit.entity,
+                distance: hit.distance,
+                point: origin + *direction * hit.distance,
+                normal: hit.normal,
+            })
+    }
+}
+
+/// Like rapier's character controller: slides against every solid collider
+/// the mover's `CollisionLayers` accept, going through sensors.
+fn move_kinematic_movers(
+    mut commands: Commands,
+    spatial: avian::SpatialQuery,
+    mut movers: Query<(
+        Entity,
+        &mut KinematicMover,
+        &Collider,
+        Option<&avian::CollisionLayers>,
+        &mut Transform,
+        Option<&mut KinematicMoverOutput>,
+    )>,
+    sensors: Query<(), With<avian::Sensor>>,
+) {
+    for (entity, mut mover, collider, layers, mut transform, mover_output) in movers.iter_mut() {
+        let Some(mut remaining) = mover.translation.take() else {
+            continue;
+        };
+
+        let start = transform.translation.xy();
+        let mut position = start;
+        let mut grounded = false;
+        let mask = layers.map_or(avian::LayerMask::ALL, |layers| layers.filters);
+        let filter = avian::SpatialQueryFilter::from_mask(mask).with_excluded_entities([entity]);
+
+        for _ in 0..MAX_SLIDES {
+            let Ok(direction) = Dir2::new(remaining) else {
+                break;
+            };
+            let config = avian::ShapeCastConfig::from_max_distance(remaining.length());
+
+            let hit = spatial
+                .shape_hits(
+                    collider,
+                    position,
+                    0.0,
+                    direction,
+                    MAX_SHAPE_HITS,
+                    &config,
+                    &filter,
+                )
+                .into_iter()
+                .filter(|hit| !sensors.contains(hit.entity))
+                .min_by(|a, b| a.distance.total_cmp(&b.distance));
+
+            match hit {
+                Some(hit) => {
+                    let travel = (hit.distance - SKIN_WIDTH).max(0.0);
+                    position += *direction * travel;
+                    remaining -= *direction * travel;
+
+                    // Slide along the surface we hit
+                    let normal = hit.normal1;
+                    remaining -= normal * remaining.dot(normal);
+
+                    if normal.y > 0.7 {
+                        grounded = true;
+                    }
+                }
+                None => {
+                    position += remaining;
+                    break;
+                }
+            }
+        }
+
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+
+        let result = KinematicMoverOutput {
+            grounded,
+            effective_translation: position - start,
+        };
+
+        match mover_output {
+            Some(mut mover_output) => *mover_output = result,
+            None => {
+                commands.entity(entity).insert(result);
+            }
+        }
+    }
+}
+
+fn forward_collision_events(
+    mut collision_started: EventReader<avian::CollisionStarted>,
+    mut collision_ended: EventReader<avian::CollisionEnded>,
+    mut started: EventWriter<CollisionStarted>,
+    mut ended: EventWriter<CollisionEnded>,
+) {
+    for avian::CollisionStarted(lhs, rhs) in collision_started.read() {
+        started.send(CollisionStarted(*lhs, *rhs));
+    }
+
+    for avian::CollisionEnded(lhs, rhs) in collision_ended.read() {
+        ended.send(CollisionEnded(*lhs, *rhs));
+    }
+}
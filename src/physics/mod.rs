@@ -0,0 +1,107 @@
+//! Thin facade over the physics engine so gameplay code can be compiled
+//! against either `bevy_rapier2d` (feature `rapier`) or `avian2d` (feature `avian`).
+
+use bevy::prelude::*;
+
+#[cfg(all(feature = "rapier", feature = "avian"))]
+compile_error!("features `rapier` and `avian` are mutually exclusive");
+
+#[cfg(not(any(feature = "rapier", feature = "avian")))]
+compile_error!("enable one physics backend: `rapier` or `avian`");
+
+#[cfg(feature = "avian")]
+mod avian;
+#[cfg(feature = "rapier")]
+mod rapier;
+
+#[cfg(feature = "avian")]
+pub use avian::*;
+#[cfg(feature = "rapier")]
+pub use rapier::*;
+
+pub struct PhysicsPlugin {
+    pub pixels_per_meter: f32,
+    pub debug_render: bool,
+}
+
+impl Default for PhysicsPlugin {
+    fn default() -> Self {
+        PhysicsPlugin {
+            pixels_per_meter: 100.0,
+            debug_render: false,
+        }
+    }
+}
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CollisionStarted>();
+        app.add_event::<CollisionEnded>();
+
+        build_backend(app, self);
+    }
+}
+
+/// Collision layer bits, combined with `|` and passed to `collision_layers`.
+pub mod layers {
+    pub const WORLD: u32 = 1 << 0;
+    pub const PLAYER: u32 = 1 << 1;
+    pub const ENEMY: u32 = 1 << 2;
+    pub const PLAYER_PROJECTILE: u32 = 1 << 3;
+    pub const ENEMY_PROJECTILE: u32 = 1 << 4;
+    pub const ALL: u32 = u32::MAX;
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BodyKind {
+    Dynamic,
+    Fixed,
+    Kinematic,
+}
+
+/// Sent when two colliders start touching, regardless of the backend.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct CollisionStarted(pub Entity, pub Entity);
+
+/// Sent when two colliders stop touching, regardless of the backend.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct CollisionEnded(pub Entity, pub Entity);
+
+#[derive(Copy, Clone, Debug)]
+pub struct RayHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RayFilter {
+    pub exclude: Option<Entity>,
+    pub include_sensors: bool,
+    /// Only hit colliders whose memberships intersect this
+    pub mask: u32,
+}
+
+impl Default for RayFilter {
+    fn default() -> Self {
+        RayFilter {
+            exclude: None,
+            include_sensors: false,
+            mask: layers::ALL,
+        }
+    }
+}
+
+/// Move-and-slide character body. Set `translation` each frame and read the
+/// result back from `KinematicMoverOutput` once the backend has moved it.
+#[derive(Component, Default)]
+pub struct KinematicMover {
+    pub translation: Option<Vec2>,
+}
+
+#[derive(Component, Default, Debug)]
+pub struct KinematicMoverOutput {
+    pub grounded: bool,
+    pub effective_translation: Vec2,
+}
@@ -0,0 +1,232 @@
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude as rapier;
+
+use super::{
+    BodyKind, CollisionEnded, CollisionStarted, KinematicMover, KinematicMoverOutput,
+    PhysicsPlugin, RayFilter, RayHit,
+};
+
+pub use bevy_rapier2d::prelude::{Collider, ColliderDisabled, RigidBody};
+
+pub(super) fn build_backend(app: &mut App, plugin: &PhysicsPlugin) {
+    app.add_plugins(
+        rapier::RapierPhysicsPlugin::<rapier::NoUserData>::pixels_per_meter(
+            plugin.pixels_per_meter,
+        )
+        .in_fixed_schedule(),
+    );
+
+    if plugin.debug_render {
+        app.add_plugins(rapier::RapierDebugRenderPlugin::default());
+    }
+
+    app.register_required_components::<KinematicMover, rapier::KinematicCharacterController>();
+    app.add_systems(
+        FixedPostUpdate,
+        push_mover_translation.before(rapier::PhysicsSet::SyncBackend),
+    );
+    app.add_systems(
+        FixedPostUpdate,
+        (read_mover_output, forward_collision_events).after(rapier::PhysicsSet::Writeback),
+    );
+}
+
+pub fn body(kind: BodyKind) -> RigidBody {
+    match kind {
+        BodyKind::Dynamic => RigidBody::Dynamic,
+        BodyKind::Fixed => RigidBody::Fixed,
+        BodyKind::Kinematic => RigidBody::KinematicPositionBased,
+    }
+}
+
+pub fn cuboid(half_x: f32, half_y: f32) -> Collider {
+    Collider::cuboid(half_x, half_y)
+}
+
+pub fn capsule(start: Vec2, end: Vec2, radius: f32) -> Collider {
+    Collider::capsule(start, end, radius)
+}
+
+pub fn ball(radius: f32) -> Collider {
+    Collider::ball(radius)
+}
+
+pub fn sensor() -> impl Bundle {
+    rapier::Sensor
+}
+
+pub fn friction(coefficient: f32) -> impl Bundle {
+    rapier::Friction::new(coefficient)
+}
+
+pub fn lock_rotation() -> impl Bundle {
+    rapier::LockedAxes::ROTATION_LOCKED
+}
+
+pub fn damping(linear: f32, angular: f32) -> impl Bundle {
+    rapier::Damping {
+        linear_damping: linear,
+        angular_damping: angular,
+    }
+}
+
+pub fn gravity_scale(scale: f32) -> impl Bundle {
+    rapier::GravityScale(scale)
+}
+
+pub fn continuous_collision() -> impl Bundle {
+    rapier::Ccd::enabled()
+}
+
+/// Also enables kinematic/fixed and kinematic/kinematic pairs, which rapier
+/// skips by default. The player and projectiles are both kinematic.
+pub fn report_collisions() -> impl Bundle {
+    (
+        rapier::ActiveEvents::COLLISION_EVENTS,
+        rapier::ActiveCollisionTypes::default()
+            | rapier::ActiveCollisionTypes::KINEMATIC_FIXED
+            | rapier::ActiveCollisionTypes::KINEMATIC_KINEMATIC,
+    )
+}
+
+/// Only collide with colliders whose memberships intersect `filters`, and
+/// the other way around.
+pub fn collision_layers(memberships: u32, filters: u32) -> impl Bundle {
+    rapier::CollisionGroups::new(
+        rapier::Group::from_bits_retain(memberships),
+        rapier::Group::from_bits_retain(filters),
+    )
+}
+
+pub fn velocity() -> impl Bundle {
+    rapier::Velocity::zero()
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct BodyVelocity {
+    velocity: &'static mut rapier::Velocity,
+}
+
+impl BodyVelocityItem<'_> {
+    pub fn linear(&self) -> Vec2 {
+        self.velocity.linvel
+    }
+
+    pub fn set_linear(&mut self, linear: Vec2) {
+        self.velocity.linvel = linear;
+    }
+
+    pub fn angular(&self) -> f32 {
+        self.velocity.angvel
+    }
+
+    pub fn set_angular(&mut self, angular: f32) {
+        self.velocity.angvel = angular;
+    }
+}
+
+#[derive(SystemParam)]
+pub struct PhysicsQuery<'w, 's> {
+    context: rapier::ReadDefaultRapierContext<'w, 's>,
+}
+
+impl PhysicsQuery<'_, '_> {
+    pub fn cast_ray(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &RayFilter,
+    ) -> Option<RayHit> {
+        let mut query_filter = rapier::QueryFilter::default();
+        if !filter.include_sensors {
+            query_filter = query_filter.exclude_sensors();
+        }
+        if let Some(entity) = filter.exclude {
+            query_filter = query_filter.exclude_rigid_body(entity);
+        }
+        query_filter = query_filter.groups(rapier::CollisionGroups::new(
+            rapier::Group::ALL,
+            rapier::Group::from_bits_retain(filter.mask),
+        ));
+
+        self.context
+            .single()
+            .cast_ray_and_get_normal(
+                origin,
+                direction.normalize_or_zero(),
+                max_distance,
+                true,
+                query_filter,
+            )
+            .map(|(entity, hit)| RayHit {
+                entity,
+                distance: hit.time_of_impact,
+                point: hit.point,
+                normal: hit.normal,
+            })
+    }
+}
+
+fn push_mover_translation(
+    mut movers: Query<(
+        &mut KinematicMover,
+        &mut rapier::KinematicCharacterController,
+        Option<&rapier::CollisionGroups>,
+    )>,
+) {
+    for (mut mover, mut controller, groups) in movers.iter_mut() {
+        if let Some(translation) = mover.translation.take() {
+            // Same filtering as the avian mover: the body's layers, no sensors
+            controller.filter_groups = groups.copied();
+            controller.filter_flags |= rapier::QueryFilterFlags::EXCLUDE_SENSORS;
+            controller.translation = Some(translation);
+        }
+    }
+}
+
+fn read_mover_output(
+    mut commands: Commands,
+    mut movers: Query<
+        (
+            Entity,
+            &rapier::KinematicCharacterControllerOutput,
+            Option<&mut KinematicMoverOutput>,
+        ),
+        With<KinematicMover>,
+    >,
+) {
+    for (entity, output, mover_output) in movers.iter_mut() {
+        let result = KinematicMoverOutput {
+            grounded: output.grounded,
+            effective_translation: output.effective_translation,
+        };
+
+        match mover_output {
+            Some(mut mover_output) => *mover_output = result,
+            None => {
+                commands.entity(entity).insert(result);
+            }
+        }
+    }
+}
+
+fn forward_collision_events(
+    mut collision_events: EventReader<rapier::CollisionEvent>,
+    mut started: EventWriter<CollisionStarted>,
+    mut ended: EventWriter<CollisionEnded>,
+) {
+    for collision_event in collision_events.read() {
+        match collision_event {
+            rapier::CollisionEvent::Started(lhs, rhs, _) => {
+                started.send(CollisionStarted(*lhs, *rhs));
+            }
+            rapier::CollisionEvent::Stopped(lhs, rhs, _) => {
+                ended.send(CollisionEnded(*lhs, *rhs));
+            }
+        }
+    }
+}
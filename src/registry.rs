@@ -0,0 +1,17 @@
+//! LDtk identifiers and IntGrid values the game spawns something for. The
+//! `validate` binary checks project files against these.
+
+pub const PLAYER: &str = "Player";
+pub const ENEMY: &str = "Enemy";
+pub const ITEM: &str = "Item";
+pub const DECORATION: &str = "Decoration";
+pub const SECRET_AREA: &str = "SecretArea";
+pub const SOUND_EMITTER: &str = "SoundEmitter";
+
+pub const ENTITIES: &[&str] = &[PLAYER, ENEMY, ITEM, DECORATION, SECRET_AREA, SOUND_EMITTER];
+
+pub const WALL: i32 = 1;
+pub const PLATFORM: i32 = 2;
+pub const BREAKABLE: i32 = 3;
+
+pub const INT_GRID_VALUES: &[i32] = &[WALL, PLATFORM, BREAKABLE];
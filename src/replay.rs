@@ -0,0 +1,374 @@
+//! Records the per-tick player actions to a file and feeds them back later.
+//! Every tick also stores a checksum of the `Character` transforms so a replay
+//! can tell exactly when it stopped matching the recording.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::character::input::{snapshot_player_actions, PlayerActions};
+use crate::character::Character;
+
+const HEADER: &str = "bevy_playground replay v2";
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayMode>();
+        app.init_resource::<Recording>();
+        app.add_event::<ReplayDiverged>();
+        app.add_systems(Startup, load_replay);
+        app.add_systems(
+            FixedPreUpdate,
+            (record_actions, replay_actions)
+                .after(snapshot_player_actions)
+                .run_if(character_exists),
+        );
+        app.add_systems(
+            FixedLast,
+            (record_checksum, verify_checksum).run_if(character_exists),
+        );
+        app.add_systems(Last, save_recording_on_exit);
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl ReplayMode {
+    /// Reads `--record <path>` or `--replay <path>` from the command line.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mode: fn(PathBuf) -> Self = match arg.as_str() {
+                "--record" => ReplayMode::Record,
+                "--replay" => ReplayMode::Replay,
+                _ => continue,
+            };
+
+            return match args.next() {
+                Some(path) if !path.starts_with("--") => mode(path.into()),
+                _ => ReplayMode::Off,
+            };
+        }
+
+        ReplayMode::Off
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TickRecord {
+    pub actions: PlayerActions,
+    pub checksum: u64,
+}
+
+/// Ticks recorded so far, or the ticks being replayed.
+#[derive(Resource, Default)]
+pub struct Recording {
+    pub ticks: Vec<TickRecord>,
+    cursor: usize,
+    diverged: bool,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a replay file",
+            ));
+        }
+
+        let ticks = lines
+            .map(parse_tick)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed tick"))?;
+
+        Ok(Recording { ticks, ..default() })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = String::from(HEADER);
+
+        for tick in &self.ticks {
+            contents.push('\n');
+            contents.push(if tick.actions.left { 'l' } else { '-' });
+            contents.push(if tick.actions.right { 'r' } else { '-' });
+            contents.push(if tick.actions.jump { 'j' } else { '-' });
+            contents.push(if tick.actions.attack { 'a' } else { '-' });
+            contents.push_str(&format!(" {:016x}", tick.checksum));
+        }
+
+        fs::write(path, contents)
+    }
+}
+
+fn parse_tick(line: &str) -> Option<TickRecord> {
+    let (flags, checksum) = line.split_once(' ')?;
+    let flags = flags.as_bytes();
+
+    if flags.len() != 4 {
+        return None;
+    }
+
+    Some(TickRecord {
+        actions: PlayerActions {
+            left: flags[0] == b'l',
+            right: flags[1] == b'r',
+            jump: flags[2] == b'j',
+            attack: flags[3] == b'a',
+        },
+        checksum: u64::from_str_radix(checksum, 16).ok()?,
+    })
+}
+
+/// Sent once, on the first tick whose checksum doesn't match the recording.
+#[derive(Event, Debug)]
+pub struct ReplayDiverged {
+    pub tick: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+/// FNV-1a over the raw bits of every `Character` transform.
+pub fn transforms_checksum<'a>(transforms: impl IntoIterator<Item = &'a Transform>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+    for transform in transforms {
+        let values = transform
+            .translation
+            .to_array()
+            .into_iter()
+            .chain(transform.rotation.to_array());
+
+        for value in values {
+            for byte in value.to_bits().to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+    }
+
+    hash
+}
+
+fn character_exists(query: Query<(), With<Character>>) -> bool {
+    !query.is_empty()
+}
+
+fn record_actions(
+    mode: Res<ReplayMode>,
+    actions: Res<PlayerActions>,
+    mut recording: ResMut<Recording>,
+) {
+    if !matches!(*mode, ReplayMode::Record(_)) {
+        return;
+    }
+
+    recording.ticks.push(TickRecord {
+        actions: *actions,
+        checksum: 0,
+    });
+}
+
+fn load_replay(mode: Res<ReplayMode>, mut recording: ResMut<Recording>) {
+    let ReplayMode::Replay(path) = &*mode else {
+        return;
+    };
+
+    match Recording::load(path) {
+        Ok(loaded) => {
+            info!("Replaying {} ticks from {:?}", loaded.ticks.len(), path);
+            *recording = loaded;
+        }
+        Err(error) => error!("Could not load replay {:?}: {}", path, error),
+    }
+}
+
+fn replay_actions(
+    mode: Res<ReplayMode>,
+    recording: Res<Recording>,
+    mut actions: ResMut<PlayerActions>,
+) {
+    if !matches!(*mode, ReplayMode::Replay(_)) {
+        return;
+    }
+
+    // Once the recording runs out the player stands still
+    *actions = match recording.ticks.get(recording.cursor) {
+        Some(tick) => tick.actions,
+        None => PlayerActions::default(),
+    };
+}
+
+fn record_checksum(
+    mode: Res<ReplayMode>,
+    characters: Query<&Transform, With<Character>>,
+    mut recording: ResMut<Recording>,
+) {
+    if !matches!(*mode, ReplayMode::Record(_)) {
+        return;
+    }
+
+    if let Some(tick) = recording.ticks.last_mut() {
+        tick.checksum = transforms_checksum(&characters);
+    }
+}
+
+fn verify_checksum(
+    mode: Res<ReplayMode>,
+    characters: Query<&Transform, With<Character>>,
+    mut recording: ResMut<Recording>,
+    mut diverged: EventWriter<ReplayDiverged>,
+) {
+    if !matches!(*mode, ReplayMode::Replay(_)) {
+        return;
+    }
+
+    let tick = recording.cursor;
+    let Some(expected) = recording.ticks.get(tick).map(|record| record.checksum) else {
+        return;
+    };
+
+    let actual = transforms_checksum(&characters);
+    if actual != expected && !recording.diverged {
+        warn!(
+            "Replay diverged at tick {}: expected {:016x}, got {:016x}",
+            tick, expected, actual
+        );
+        recording.diverged = true;
+        diverged.send(ReplayDiverged {
+            tick,
+            expected,
+            actual,
+        });
+    }
+
+    recording.cursor += 1;
+    if recording.cursor == recording.ticks.len() {
+        info!("Replay finished after {} ticks", recording.cursor);
+    }
+}
+
+fn save_recording_on_exit(
+    mut exit: EventReader<AppExit>,
+    mode: Res<ReplayMode>,
+    recording: Res<Recording>,
+) {
+    let ReplayMode::Record(path) = &*mode else {
+        return;
+    };
+
+    if exit.read().next().is_none() {
+        return;
+    }
+
+    match recording.save(path) {
+        Ok(()) => info!("Saved {} ticks to {:?}", recording.ticks.len(), path),
+        Err(error) => error!("Could not save recording {:?}: {}", path, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn mode_from_args() {
+        assert_eq!(ReplayMode::from_args(args("")), ReplayMode::Off);
+        assert_eq!(
+            ReplayMode::from_args(args("--record run.replay")),
+            ReplayMode::Record("run.replay".into())
+        );
+        assert_eq!(
+            ReplayMode::from_args(args("--time-trial Boss_room --replay run.replay")),
+            ReplayMode::Replay("run.replay".into())
+        );
+        // Flags are found whatever comes before them
+        assert_eq!(
+            ReplayMode::from_args(args("--windowed --replay run.replay")),
+            ReplayMode::Replay("run.replay".into())
+        );
+        // A flag without its path is ignored
+        assert_eq!(ReplayMode::from_args(args("--record")), ReplayMode::Off);
+        assert_eq!(
+            ReplayMode::from_args(args("--record --windowed")),
+            ReplayMode::Off
+        );
+    }
+
+    #[test]
+    fn recording_round_trips() {
+        let ticks = vec![
+            TickRecord {
+                actions: PlayerActions::default(),
+                checksum: 0,
+            },
+            TickRecord {
+                actions: PlayerActions {
+                    left: true,
+                    jump: true,
+                    ..default()
+                },
+                checksum: 0x0123_4567_89ab_cdef,
+            },
+            TickRecord {
+                actions: PlayerActions {
+                    right: true,
+                    attack: true,
+                    ..default()
+                },
+                checksum: u64::MAX,
+            },
+        ];
+        let path = std::env::temp_dir().join(format!("replay-{}.replay", std::process::id()));
+
+        Recording {
+            ticks: ticks.clone(),
+            ..default()
+        }
+        .save(&path)
+        .unwrap();
+        let loaded = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap().ticks, ticks);
+    }
+
+    #[test]
+    fn malformed_ticks_are_rejected() {
+        assert!(parse_tick("lrja 00000000000000ff").is_some());
+        assert!(parse_tick("lrj 00000000000000ff").is_none());
+        assert!(parse_tick("lrja zz").is_none());
+        assert!(parse_tick("lrja").is_none());
+    }
+
+    #[test]
+    fn checksum_follows_transforms() {
+        let still = Transform::from_xyz(16.0, 32.0, 0.0);
+        let moved = Transform::from_xyz(16.0, 32.5, 0.0);
+
+        assert_eq!(transforms_checksum([&still]), transforms_checksum([&still]));
+        assert_ne!(transforms_checksum([&still]), transforms_checksum([&moved]));
+        assert_ne!(
+            transforms_checksum([&still, &moved]),
+            transforms_checksum([&moved, &still])
+        );
+    }
+}
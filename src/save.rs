@@ -0,0 +1,96 @@
+//! Progress that outlives a session, stored as JSON in the `DataDir`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+use serde::{Deserialize, Serialize};
+
+const SAVE_PATH: &str = "save.json";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DataDir>();
+        app.init_resource::<SaveData>();
+        app.add_event::<SaveRequested>();
+        app.add_systems(Startup, load_save);
+        app.add_systems(Last, write_save);
+    }
+}
+
+/// Directory holding saves, settings and time-trial runs. Defaults to the
+/// working directory; insert it before the plugins to move them elsewhere.
+#[derive(Resource, Clone, Debug)]
+pub struct DataDir(pub PathBuf);
+
+impl Default for DataDir {
+    fn default() -> Self {
+        DataDir(PathBuf::from("."))
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SaveData {
+    /// Identifiers of the levels whose boss has been beaten
+    pub defeated_bosses: BTreeSet<String>,
+    /// Grid coordinates of destroyed breakable walls, keyed by level IID
+    pub broken_walls: BTreeMap<String, BTreeSet<(i32, i32)>>,
+}
+
+impl SaveData {
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(dir.join(SAVE_PATH))?;
+        serde_json::from_str(&contents).map_err(io::Error::from)
+    }
+
+    pub fn is_wall_broken(&self, level_iid: &str, coords: GridCoords) -> bool {
+        self.broken_walls
+            .get(level_iid)
+            .is_some_and(|walls| walls.contains(&(coords.x, coords.y)))
+    }
+
+    pub fn break_wall(&mut self, level_iid: &str, coords: GridCoords) {
+        self.broken_walls
+            .entry(level_iid.to_string())
+            .or_default()
+            .insert((coords.x, coords.y));
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::from)?;
+        fs::write(dir.join(SAVE_PATH), contents)
+    }
+}
+
+/// Writes `SaveData` to disk at the end of the frame.
+#[derive(Event, Default)]
+pub struct SaveRequested;
+
+fn load_save(data_dir: Res<DataDir>, mut save: ResMut<SaveData>) {
+    match SaveData::load(&data_dir.0) {
+        Ok(loaded) => *save = loaded,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => warn!("Ignoring save {}: {}", SAVE_PATH, error),
+    }
+}
+
+fn write_save(
+    mut requests: EventReader<SaveRequested>,
+    data_dir: Res<DataDir>,
+    save: Res<SaveData>,
+) {
+    if requests.read().count() == 0 {
+        return;
+    }
+
+    match save.save(&data_dir.0) {
+        Ok(()) => info!("Game saved"),
+        Err(error) => error!("Could not write {}: {}", SAVE_PATH, error),
+    }
+}
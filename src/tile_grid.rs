@@ -1,60 +1,877 @@
+//! In-game level editing: toggle with F2, pick a value with 1-3, left click to
+//! place, right click to erase, Ctrl+Z / Ctrl+Y to undo and redo. Edits go to
+//! the `Collisions` IntGrid layer of whichever level is under the cursor.
+//! Delete removes the LDtk entity under the cursor, which can't be undone.
+//! Tab picks an entity and Insert places it in the cell under the cursor; it
+//! shows as a placeholder until the exported project is loaded.
+//! Edits are replayed whenever their level spawns again, so restarts and
+//! streaming keep them, like the navigation graphs do.
+
+use std::collections::{HashMap, HashSet};
+
 use bevy::prelude::*;
-use toolkit::cursor_tracking::CursorPosition;
+use bevy_ecs_ldtk::ldtk::{EntityDefinition, Type};
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::breakable::Breakable;
+use crate::cursor_tracking::{CursorPosition, CursorTrackingPlugin};
+use crate::navigation::{NavGraphs, COLLISIONS_LAYER};
+use crate::registry;
+use crate::{Platform, Wall};
 
 pub struct TileGridPlugin;
 
 impl Plugin for TileGridPlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(CursorTrackingPlugin);
         app.add_event::<GridClicked>();
-        app.add_systems(Update, grid_clicked);
-        app.add_systems(Update, detect_clicked_grid);
+        app.add_event::<SetCell>();
+        app.add_event::<AddEntity>();
+        app.init_resource::<TileEditor>();
+        app.init_resource::<EditHistory>();
+        app.init_resource::<CellIndex>();
+        app.init_resource::<LevelEdits>();
+        app.add_systems(
+            Update,
+            (
+                prune_cells,
+                index_cells,
+                toggle_editor,
+                reapply_level_edits,
+                detect_clicked_grid.run_if(editor_enabled),
+                (
+                    paint_clicked_cells,
+                    undo_redo,
+                    remove_hovered_entity,
+                    place_hovered_entity,
+                )
+                    .run_if(editor_enabled),
+                (apply_cell_edits, spawn_added_entities),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// A cell of a level's `Collisions` layer under the cursor.
+#[derive(Event, Debug, Clone)]
+pub struct GridClicked {
+    pub level_iid: LevelIid,
+    pub coords: GridCoords,
+    pub button: MouseButton,
+}
+
+/// Replace the IntGrid value of a cell; 0 erases it.
+#[derive(Event, Debug, Clone)]
+pub struct SetCell {
+    pub level_iid: LevelIid,
+    pub coords: GridCoords,
+    pub value: i32,
+}
+
+/// Show an entity placed with the editor in a spawned level.
+#[derive(Event, Debug, Clone)]
+pub struct AddEntity {
+    pub level_iid: LevelIid,
+    pub entity: AddedEntity,
+}
+
+/// Entities the editor can place, in Tab order. There is only one player.
+const PLACEABLE: &[&str] = &[
+    registry::ENEMY,
+    registry::ITEM,
+    registry::DECORATION,
+    registry::SOUND_EMITTER,
+];
+
+#[derive(Resource, Debug)]
+pub struct TileEditor {
+    pub enabled: bool,
+    /// IntGrid value placed by a left click
+    pub brush: i32,
+    /// LDtk entity identifier placed by Insert
+    pub entity_brush: &'static str,
+}
+
+impl Default for TileEditor {
+    fn default() -> Self {
+        TileEditor {
+            enabled: false,
+            brush: registry::WALL,
+            entity_brush: PLACEABLE[0],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CellEdit {
+    level_iid: LevelIid,
+    coords: GridCoords,
+    before: i32,
+    after: i32,
+}
+
+#[derive(Resource, Default)]
+struct EditHistory {
+    undo: Vec<CellEdit>,
+    redo: Vec<CellEdit>,
+}
+
+/// IntGrid tiles of the `Collisions` layers, by level IID and grid position.
+/// Tiles despawned by a restart or by streaming their level out are pruned.
+#[derive(Resource, Default)]
+struct CellIndex(HashMap<(String, GridCoords), Entity>);
+
+/// Everything changed at runtime, by level IID.
+#[derive(Resource, Default, Debug)]
+pub struct LevelEdits {
+    /// New IntGrid values; cells set back to 0 are kept so erasing can be
+    /// told apart from never touching a cell
+    pub cells: HashMap<String, HashMap<GridCoords, i32>>,
+    /// IIDs of LDtk entities removed from the level
+    pub removed_entities: HashMap<String, HashSet<String>>,
+    /// Entities placed with the editor, in placement order
+    pub added_entities: HashMap<String, Vec<AddedEntity>>,
+}
+
+/// An LDtk entity placed with the editor, which the project doesn't have yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddedEntity {
+    /// Freshly generated, so it can't clash with the project's
+    pub iid: String,
+    pub identifier: String,
+    /// Cell of the level's entity layer the entity's pivot snaps to
+    pub coords: GridCoords,
+}
+
+/// A random version 4 UUID, the format LDtk uses for IIDs.
+pub fn fresh_iid() -> String {
+    let mut bits: u128 = rand::random();
+    bits = (bits & !(0xF << 76)) | (0x4 << 76);
+    bits = (bits & !(0x3 << 62)) | (0x2 << 62);
+
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        bits >> 96,
+        (bits >> 80) & 0xFFFF,
+        (bits >> 64) & 0xFFFF,
+        (bits >> 48) & 0xFFFF,
+        bits & 0xFFFF_FFFF_FFFF
+    )
+}
+
+fn editor_enabled(editor: Res<TileEditor>) -> bool {
+    editor.enabled
+}
+
+fn collisions_layer_level(
+    layer: Entity,
+    layers: &Query<(&LayerMetadata, &Parent)>,
+    levels: &Query<(&LevelIid, &GlobalTransform)>,
+) -> Option<LevelIid> {
+    let (metadata, parent) = layers.get(layer).ok()?;
+    if metadata.identifier != COLLISIONS_LAYER {
+        return None;
+    }
+    levels.get(parent.get()).ok().map(|(iid, _)| iid.clone())
+}
+
+fn index_cells(
+    mut index: ResMut<CellIndex>,
+    cells: Query<(Entity, &GridCoords, &Parent), Added<IntGridCell>>,
+    layers: Query<(&LayerMetadata, &Parent)>,
+    levels: Query<(&LevelIid, &GlobalTransform)>,
+) {
+    for (entity, coords, parent) in cells.iter() {
+        if let Some(level_iid) = collisions_layer_level(parent.get(), &layers, &levels) {
+            index.0.insert((level_iid.to_string(), *coords), entity);
+        }
+    }
+}
+
+fn prune_cells(mut index: ResMut<CellIndex>, mut removed: RemovedComponents<IntGridCell>) {
+    let removed: HashSet<Entity> = removed.read().collect();
+    if !removed.is_empty() {
+        index.0.retain(|_, entity| !removed.contains(entity));
+    }
+}
+
+fn toggle_editor(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<TileEditor>) {
+    if keys.just_pressed(KeyCode::F2) {
+        editor.enabled = !editor.enabled;
+        info!("Tile editor {}", if editor.enabled { "on" } else { "off" });
+    }
+
+    for (key, value) in [
+        (KeyCode::Digit1, registry::WALL),
+        (KeyCode::Digit2, registry::PLATFORM),
+        (KeyCode::Digit3, registry::BREAKABLE),
+    ] {
+        if editor.enabled && keys.just_pressed(key) {
+            editor.brush = value;
+            info!("Brush: {}", value);
+        }
+    }
+
+    if editor.enabled && keys.just_pressed(KeyCode::Tab) {
+        let next = PLACEABLE
+            .iter()
+            .position(|identifier| *identifier == editor.entity_brush)
+            .map_or(0, |index| (index + 1) % PLACEABLE.len());
+        editor.entity_brush = PLACEABLE[next];
+        info!("Entity brush: {}", editor.entity_brush);
+    }
+}
+
+/// Levels respawn from the LDtk project, so edited cells come back with their
+/// original values, removed entities come back and placed ones are missing.
+fn reapply_level_edits(
+    mut commands: Commands,
+    mut level_events: EventReader<LevelEvent>,
+    edits: Res<LevelEdits>,
+    entities: Query<(Entity, &EntityInstance)>,
+    mut set_cell: EventWriter<SetCell>,
+    mut add_entity: EventWriter<AddEntity>,
+) {
+    for event in level_events.read() {
+        // Transformed comes once the level and its layers have been placed
+        let LevelEvent::Transformed(level_iid) = event else {
+            continue;
+        };
+
+        if let Some(cells) = edits.cells.get(level_iid.as_str()) {
+            for (coords, value) in cells {
+                set_cell.send(SetCell {
+                    level_iid: level_iid.clone(),
+                    coords: *coords,
+                    value: *value,
+                });
+            }
+        }
+
+        for entity in edits
+            .added_entities
+            .get(level_iid.as_str())
+            .into_iter()
+            .flatten()
+        {
+            add_entity.send(AddEntity {
+                level_iid: level_iid.clone(),
+                entity: entity.clone(),
+            });
+        }
+
+        if let Some(removed) = edits.removed_entities.get(level_iid.as_str()) {
+            for (entity, instance) in entities.iter() {
+                if removed.contains(&instance.iid) {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
     }
 }
 
-// Add new system to detect clicked grid
 fn detect_clicked_grid(
     cursor_position: Res<CursorPosition>,
-    grid_query: Query<(Entity, &GlobalTransform), With<Grid>>,
     mouse: Res<ButtonInput<MouseButton>>,
+    levels: Query<(&LevelIid, &GlobalTransform)>,
+    layers: Query<(&LayerMetadata, &Parent)>,
     mut events: EventWriter<GridClicked>,
 ) {
-    if mouse.just_pressed(MouseButton::Left) {
-        dbg!(cursor_position.0);
-        let clicked = cursor_position.0;
+    let Some(cursor) = cursor_position.0 else {
+        return;
+    };
 
-        // Calculate the closest grid tile
-        let closest = grid_query
-            .iter()
-            .map(|(entity, global_transform)| {
-                // Calculate de distance from the middle of the grid tile
-                let middle = global_transform.translation().xy();
-                let distance = middle.distance(clicked);
+    for button in [MouseButton::Left, MouseButton::Right] {
+        if !mouse.just_pressed(button) {
+            continue;
+        }
+
+        for (metadata, parent) in layers.iter() {
+            if metadata.identifier != COLLISIONS_LAYER {
+                continue;
+            }
+            let Ok((level_iid, level_transform)) = levels.get(parent.get()) else {
+                continue;
+            };
+
+            // Levels are anchored at their bottom-left corner, like GridCoords
+            let local = cursor - level_transform.translation().xy();
+            let cell = (local / metadata.grid_size as f32).floor();
+            let coords = GridCoords::new(cell.x as i32, cell.y as i32);
+
+            if coords.x >= 0
+                && coords.y >= 0
+                && coords.x < metadata.c_wid
+                && coords.y < metadata.c_hei
+            {
+                events.send(GridClicked {
+                    level_iid: level_iid.clone(),
+                    coords,
+                    button,
+                });
+            }
+        }
+    }
+}
+
+fn cell_value(
+    index: &CellIndex,
+    cells: &Query<&IntGridCell>,
+    level_iid: &LevelIid,
+    coords: GridCoords,
+) -> i32 {
+    index
+        .0
+        .get(&(level_iid.to_string(), coords))
+        .and_then(|entity| cells.get(*entity).ok())
+        .map_or(0, |cell| cell.value)
+}
+
+fn paint_clicked_cells(
+    editor: Res<TileEditor>,
+    index: Res<CellIndex>,
+    cells: Query<&IntGridCell>,
+    mut clicks: EventReader<GridClicked>,
+    mut history: ResMut<EditHistory>,
+    mut set_cell: EventWriter<SetCell>,
+) {
+    for click in clicks.read() {
+        let after = match click.button {
+            MouseButton::Left => editor.brush,
+            _ => 0,
+        };
+        let before = cell_value(&index, &cells, &click.level_iid, click.coords);
+        if before == after {
+            continue;
+        }
+
+        history.undo.push(CellEdit {
+            level_iid: click.level_iid.clone(),
+            coords: click.coords,
+            before,
+            after,
+        });
+        history.redo.clear();
+        set_cell.send(SetCell {
+            level_iid: click.level_iid.clone(),
+            coords: click.coords,
+            value: after,
+        });
+    }
+}
+
+fn undo_redo(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut history: ResMut<EditHistory>,
+    mut set_cell: EventWriter<SetCell>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !ctrl {
+        return;
+    }
+
+    let redo = keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ));
+    let undo = !redo && keys.just_pressed(KeyCode::KeyZ);
+
+    if undo {
+        if let Some(edit) = history.undo.pop() {
+            set_cell.send(SetCell {
+                level_iid: edit.level_iid.clone(),
+                coords: edit.coords,
+                value: edit.before,
+            });
+            history.redo.push(edit);
+        }
+    } else if redo {
+        if let Some(edit) = history.redo.pop() {
+            set_cell.send(SetCell {
+                level_iid: edit.level_iid.clone(),
+                coords: edit.coords,
+                value: edit.after,
+            });
+            history.undo.push(edit);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_cell_edits(
+    mut commands: Commands,
+    mut events: EventReader<SetCell>,
+    mut index: ResMut<CellIndex>,
+    mut edits: ResMut<LevelEdits>,
+    mut nav_graphs: ResMut<NavGraphs>,
+    levels: Query<(Entity, &LevelIid, &GlobalTransform)>,
+    layers: Query<(Entity, &LayerMetadata, &Parent, &GlobalTransform)>,
+) {
+    for event in events.read() {
+        let key = (event.level_iid.to_string(), event.coords);
 
-                (entity, distance)
+        // Despawning the tile takes its collider along with it
+        if let Some(mut tile) = index
+            .0
+            .remove(&key)
+            .and_then(|entity| commands.get_entity(entity))
+        {
+            tile.despawn_recursive();
+        }
+
+        if let Some(graph) = nav_graphs.get_mut(&event.level_iid) {
+            graph.set_value(event.coords, event.value);
+        }
+        edits
+            .cells
+            .entry(event.level_iid.to_string())
+            .or_default()
+            .insert(event.coords, event.value);
+
+        if event.value == 0 {
+            continue;
+        }
+
+        let Some((level_entity, _, level_transform)) =
+            levels.iter().find(|(_, iid, _)| **iid == event.level_iid)
+        else {
+            continue;
+        };
+        let Some((layer_entity, metadata, _, layer_transform)) =
+            layers.iter().find(|(_, metadata, parent, _)| {
+                metadata.identifier == COLLISIONS_LAYER && parent.get() == level_entity
             })
-            .min_by_key(|(_, distance)| (*distance) as i32);
+        else {
+            continue;
+        };
 
-        if let Some((entity, distance)) = closest {
-            // Check if it's within threshold
-            if distance < 30.0 {
-                info!("Clicked grid tile distance: {}", distance);
-                events.send(GridClicked(entity));
+        let grid_size = metadata.grid_size as f32;
+        let center = level_transform.translation().xy()
+            + (Vec2::new(event.coords.x as f32, event.coords.y as f32) + 0.5) * grid_size;
+        let local = center - layer_transform.translation().xy();
+
+        let mut tile = commands.spawn((
+            event.coords,
+            IntGridCell { value: event.value },
+            Sprite::from_color(cell_color(event.value), Vec2::splat(grid_size)),
+            Transform::from_translation(local.extend(0.0)),
+        ));
+        match event.value {
+            registry::WALL => {
+                tile.insert(Wall);
+            }
+            registry::PLATFORM => {
+                tile.insert(Platform);
             }
+            registry::BREAKABLE => {
+                tile.insert(Breakable);
+            }
+            _ => {}
+        }
+
+        let tile = tile.id();
+        commands.entity(layer_entity).add_child(tile);
+        index.0.insert(key, tile);
+    }
+}
+
+/// Stand-in colors for placed cells, which have no auto-layer tiles.
+fn cell_color(value: i32) -> Color {
+    match value {
+        registry::WALL => Color::srgb(0.69, 0.51, 0.3),
+        registry::PLATFORM => Color::srgb(0.45, 0.59, 0.9),
+        _ => Color::srgb(0.55, 0.35, 0.24),
+    }
+}
+
+fn remove_hovered_entity(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    cursor_position: Res<CursorPosition>,
+    mut edits: ResMut<LevelEdits>,
+    entities: Query<(Entity, &EntityInstance, &GlobalTransform, &Parent)>,
+    parents: Query<&Parent>,
+    levels: Query<&LevelIid>,
+) {
+    let Some(cursor) = cursor_position.0 else {
+        return;
+    };
+    if !keys.just_pressed(KeyCode::Delete) {
+        return;
+    }
+
+    for (entity, instance, transform, layer) in entities.iter() {
+        let half_size = Vec2::new(instance.width as f32, instance.height as f32) / 2.0;
+        let offset = cursor - transform.translation().xy();
+        if offset.x.abs() > half_size.x || offset.y.abs() > half_size.y {
+            continue;
+        }
+
+        let Some(level_iid) = parents
+            .get(layer.get())
+            .ok()
+            .and_then(|level| levels.get(level.get()).ok())
+        else {
+            continue;
+        };
+
+        debug!("Removed {} {}", instance.identifier, instance.iid);
+        // Removing an entity placed with the editor just forgets it
+        let added = edits
+            .added_entities
+            .entry(level_iid.to_string())
+            .or_default();
+        let placed = added.len();
+        added.retain(|added| added.iid != instance.iid);
+        if added.len() == placed {
+            edits
+                .removed_entities
+                .entry(level_iid.to_string())
+                .or_default()
+                .insert(instance.iid.clone());
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn place_hovered_entity(
+    keys: Res<ButtonInput<KeyCode>>,
+    cursor_position: Res<CursorPosition>,
+    editor: Res<TileEditor>,
+    mut edits: ResMut<LevelEdits>,
+    levels: Query<(&LevelIid, &GlobalTransform)>,
+    layers: Query<(&LayerMetadata, &Parent)>,
+    mut add_entity: EventWriter<AddEntity>,
+) {
+    let Some(cursor) = cursor_position.0 else {
+        return;
+    };
+    if !keys.just_pressed(KeyCode::Insert) {
+        return;
+    }
+
+    for (metadata, parent) in layers.iter() {
+        if metadata.layer_instance_type != Type::Entities {
+            continue;
+        }
+        let Ok((level_iid, level_transform)) = levels.get(parent.get()) else {
+            continue;
+        };
+
+        let local = cursor - level_transform.translation().xy();
+        let cell = (local / metadata.grid_size as f32).floor();
+        let coords = GridCoords::new(cell.x as i32, cell.y as i32);
+        if coords.x < 0 || coords.y < 0 || coords.x >= metadata.c_wid || coords.y >= metadata.c_hei
+        {
+            continue;
         }
+
+        let entity = AddedEntity {
+            iid: fresh_iid(),
+            identifier: editor.entity_brush.to_string(),
+            coords,
+        };
+        info!("Placed {} {}", entity.identifier, entity.iid);
+        edits
+            .added_entities
+            .entry(level_iid.to_string())
+            .or_default()
+            .push(entity.clone());
+        add_entity.send(AddEntity {
+            level_iid: level_iid.clone(),
+            entity,
+        });
+        return;
     }
 }
 
-fn grid_clicked(mut commands: Commands, mut event: EventReader<GridClicked>) {
-    for evt in event.read() {
-        info!("Grid clicked at {:?}", evt.0);
+/// Center of an entity snapped to `coords`, relative to the level's
+/// bottom-left corner. LDtk puts the pivot at the same spot of the cell as it
+/// is of the entity.
+pub fn entity_center(definition: &EntityDefinition, coords: GridCoords, grid_size: f32) -> Vec2 {
+    let size = Vec2::new(definition.width as f32, definition.height as f32);
+    // Pivots are measured from the top-left, like everything else in LDtk
+    let pivot = Vec2::new(definition.pivot_x, 1.0 - definition.pivot_y);
+    let cell = Vec2::new(coords.x as f32, coords.y as f32);
+
+    (cell + pivot) * grid_size + (Vec2::splat(0.5) - pivot) * size
+}
+
+/// Placed entities have no gameplay until the exported project is loaded;
+/// until then they show as their LDtk color.
+fn spawn_added_entities(
+    mut commands: Commands,
+    mut events: EventReader<AddEntity>,
+    ldtk_projects: Query<&LdtkProjectHandle>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    levels: Query<(Entity, &LevelIid, &GlobalTransform)>,
+    layers: Query<(Entity, &LayerMetadata, &Parent, &GlobalTransform)>,
+) {
+    let Some(ldtk_project) = ldtk_projects
+        .get_single()
+        .ok()
+        .and_then(|handle| ldtk_project_assets.get(handle))
+    else {
+        return;
+    };
+
+    for event in events.read() {
+        let Some(definition) = ldtk_project
+            .json_data()
+            .defs
+            .entities
+            .iter()
+            .find(|definition| definition.identifier == event.entity.identifier)
+        else {
+            warn!("No LDtk entity definition for {}", event.entity.identifier);
+            continue;
+        };
+        let Some((level_entity, _, level_transform)) =
+            levels.iter().find(|(_, iid, _)| **iid == event.level_iid)
+        else {
+            continue;
+        };
+        let Some((layer_entity, metadata, _, layer_transform)) =
+            layers.iter().find(|(_, metadata, parent, _)| {
+                metadata.layer_instance_type == Type::Entities && parent.get() == level_entity
+            })
+        else {
+            continue;
+        };
+
+        let center = level_transform.translation().xy()
+            + entity_center(definition, event.entity.coords, metadata.grid_size as f32);
+        let local = center - layer_transform.translation().xy();
+        let size = Vec2::new(definition.width as f32, definition.height as f32);
 
-        // Despawn the grid tile
-        commands.entity(evt.0).despawn_recursive();
+        let entity = commands
+            .spawn((
+                Name::new(format!("{} (placed)", definition.identifier)),
+                EntityInstance {
+                    identifier: definition.identifier.clone(),
+                    iid: event.entity.iid.clone(),
+                    def_uid: definition.uid,
+                    width: definition.width,
+                    height: definition.height,
+                    ..default()
+                },
+                Sprite::from_color(definition.color, size),
+                Transform::from_translation(local.extend(0.0)),
+            ))
+            .id();
+        commands.entity(layer_entity).add_child(entity);
     }
 }
 
-#[derive(Event)]
-pub struct GridClicked(Entity);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(value_before: i32, value_after: i32) -> CellEdit {
+        CellEdit {
+            level_iid: LevelIid::new("level"),
+            coords: GridCoords::new(2, 3),
+            before: value_before,
+            after: value_after,
+        }
+    }
+
+    fn undo_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<EditHistory>()
+            .add_event::<SetCell>()
+            .add_systems(Update, undo_redo);
+        app
+    }
+
+    /// Presses Ctrl with `keys` for one frame and returns the values set.
+    fn press(app: &mut App, keys: &[KeyCode]) -> Vec<i32> {
+        {
+            let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            input.press(KeyCode::ControlLeft);
+            for key in keys {
+                input.press(*key);
+            }
+        }
+        app.update();
+        {
+            let mut input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+            input.release_all();
+            input.clear();
+        }
+
+        app.world_mut()
+            .resource_mut::<Events<SetCell>>()
+            .drain()
+            .map(|event| event.value)
+            .collect()
+    }
+
+    #[test]
+    fn undo_and_redo_replay_edits_in_order() {
+        let mut app = undo_app();
+        app.world_mut().resource_mut::<EditHistory>().undo = vec![edit(0, 1), edit(1, 3)];
+
+        assert_eq!(press(&mut app, &[KeyCode::KeyZ]), vec![1]);
+        assert_eq!(press(&mut app, &[KeyCode::KeyZ]), vec![0]);
+        assert_eq!(press(&mut app, &[KeyCode::KeyZ]), Vec::<i32>::new());
+        assert_eq!(press(&mut app, &[KeyCode::KeyY]), vec![1]);
+        assert_eq!(
+            press(&mut app, &[KeyCode::ShiftLeft, KeyCode::KeyZ]),
+            vec![3]
+        );
+        assert_eq!(press(&mut app, &[KeyCode::KeyY]), Vec::<i32>::new());
+
+        let history = app.world().resource::<EditHistory>();
+        assert_eq!(history.undo.len(), 2);
+        assert!(history.redo.is_empty());
+    }
+
+    #[test]
+    fn undo_needs_ctrl() {
+        let mut app = undo_app();
+        app.world_mut().resource_mut::<EditHistory>().undo = vec![edit(0, 1)];
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyZ);
+        app.update();
+
+        assert_eq!(app.world().resource::<EditHistory>().undo.len(), 1);
+    }
+
+    #[test]
+    fn despawned_cells_leave_the_index() {
+        let mut app = App::new();
+        app.init_resource::<CellIndex>()
+            .init_resource::<LevelEdits>()
+            .init_resource::<NavGraphs>()
+            .add_event::<SetCell>()
+            .add_systems(Update, (prune_cells, apply_cell_edits).chain());
 
-#[derive(Component)]
-pub struct Grid;
+        let key = ("level".to_string(), GridCoords::new(2, 3));
+        let tile = app.world_mut().spawn(IntGridCell { value: 1 }).id();
+        app.world_mut()
+            .resource_mut::<CellIndex>()
+            .0
+            .insert(key.clone(), tile);
+        app.update();
+
+        // Like a restart despawning the level under the editor
+        app.world_mut().despawn(tile);
+        app.update();
+        assert!(!app.world().resource::<CellIndex>().0.contains_key(&key));
+
+        // A stale entry left behind must not take the edit down with it
+        let stale = app.world_mut().spawn_empty().id();
+        app.world_mut().despawn(stale);
+        app.world_mut()
+            .resource_mut::<CellIndex>()
+            .0
+            .insert(key.clone(), stale);
+        app.world_mut().send_event(SetCell {
+            level_iid: LevelIid::new("level"),
+            coords: key.1,
+            value: 0,
+        });
+        app.update();
+
+        let edits = app.world().resource::<LevelEdits>();
+        assert_eq!(edits.cells["level"][&key.1], 0);
+    }
+
+    #[test]
+    fn respawned_levels_get_their_edits_back() {
+        let mut app = App::new();
+        app.init_resource::<CellIndex>()
+            .init_resource::<LevelEdits>()
+            .init_resource::<NavGraphs>()
+            .add_event::<SetCell>()
+            .add_event::<AddEntity>()
+            .add_event::<LevelEvent>()
+            .add_systems(Update, (reapply_level_edits, apply_cell_edits).chain());
+
+        let coords = GridCoords::new(2, 3);
+        {
+            let mut edits = app.world_mut().resource_mut::<LevelEdits>();
+            edits
+                .cells
+                .insert("level".to_string(), HashMap::from([(coords, 0)]));
+            edits
+                .removed_entities
+                .insert("level".to_string(), HashSet::from(["removed".to_string()]));
+            edits.added_entities.insert(
+                "level".to_string(),
+                vec![AddedEntity {
+                    iid: "placed".to_string(),
+                    identifier: registry::ENEMY.to_string(),
+                    coords,
+                }],
+            );
+        }
+
+        // What the level spawns with, straight from the LDtk project
+        let wall = app.world_mut().spawn(IntGridCell { value: 1 }).id();
+        let removed = app
+            .world_mut()
+            .spawn(EntityInstance {
+                iid: "removed".to_string(),
+                ..default()
+            })
+            .id();
+        let kept = app
+            .world_mut()
+            .spawn(EntityInstance {
+                iid: "kept".to_string(),
+                ..default()
+            })
+            .id();
+        app.world_mut()
+            .resource_mut::<CellIndex>()
+            .0
+            .insert(("level".to_string(), coords), wall);
+
+        app.world_mut()
+            .send_event(LevelEvent::Transformed(LevelIid::new("level")));
+        app.update();
+
+        assert!(!app.world().entities().contains(wall));
+        assert!(!app.world().entities().contains(removed));
+        assert!(app.world().entities().contains(kept));
+
+        let added: Vec<String> = app
+            .world_mut()
+            .resource_mut::<Events<AddEntity>>()
+            .drain()
+            .map(|event| event.entity.iid)
+            .collect();
+        assert_eq!(added, vec!["placed".to_string()]);
+    }
+
+    #[test]
+    fn fresh_iids_are_version_4_uuids() {
+        let iid = fresh_iid();
+
+        let groups: Vec<usize> = iid.split('-').map(str::len).collect();
+        assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+        assert_eq!(iid.as_bytes()[14], b'4');
+        assert!(matches!(iid.as_bytes()[19], b'8' | b'9' | b'a' | b'b'));
+        assert_ne!(fresh_iid(), iid);
+    }
+
+    #[test]
+    fn entities_snap_their_pivot_to_the_cell() {
+        // Bottom-center pivot, like the shipped enemies and items
+        let definition = EntityDefinition {
+            width: 24,
+            height: 24,
+            pivot_x: 0.5,
+            pivot_y: 1.0,
+            ..default()
+        };
+
+        let center = entity_center(&definition, GridCoords::new(2, 0), 16.0);
+
+        assert_eq!(center, Vec2::new(40.0, 12.0));
+    }
+}
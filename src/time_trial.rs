@@ -0,0 +1,343 @@
+//! Time trials between two levels of the LDtk world. The best run of every
+//! route is kept on disk and replayed as a translucent ghost next to the player.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::character::Character;
+use crate::level::{CurrentLevel, RestartRequested};
+use crate::save::DataDir;
+
+const HEADER: &str = "bevy_playground ghost v1";
+const RUNS_DIR: &str = "runs";
+const GHOST_ALPHA: f32 = 0.4;
+
+pub struct TimeTrialPlugin;
+
+impl Plugin for TimeTrialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DataDir>();
+        app.init_resource::<TimeTrial>();
+        app.init_resource::<TrialRun>();
+        app.add_event::<TrialFinished>();
+        app.add_systems(Startup, load_best_run);
+        app.add_systems(
+            FixedUpdate,
+            (start_run, advance_run, finish_run, move_ghost)
+                .chain()
+                .run_if(trial_enabled),
+        );
+        app.add_systems(Update, reset_run_on_respawn.run_if(trial_enabled));
+    }
+}
+
+/// Route being raced. No target means time trials are off.
+#[derive(Resource, Clone, Debug)]
+pub struct TimeTrial {
+    pub start: String,
+    pub target: Option<String>,
+}
+
+impl Default for TimeTrial {
+    fn default() -> Self {
+        TimeTrial {
+            start: "Entrance".to_string(),
+            target: None,
+        }
+    }
+}
+
+impl TimeTrial {
+    /// Reads `--time-trial <target level>` from the command line.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut trial = TimeTrial::default();
+
+        while let Some(arg) = args.next() {
+            if arg == "--time-trial" {
+                trial.target = args.next().filter(|target| !target.starts_with("--"));
+            }
+        }
+
+        trial
+    }
+
+    fn route_path(&self, dir: &Path) -> Option<PathBuf> {
+        let target = self.target.as_ref()?;
+        Some(
+            dir.join(RUNS_DIR)
+                .join(format!("{}__{}.ghost", self.start, target)),
+        )
+    }
+}
+
+/// A finished or in-progress run: one player position per fixed tick.
+#[derive(Clone, Debug, Default)]
+pub struct RunTrajectory(pub Vec<Vec2>);
+
+impl RunTrajectory {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a ghost file",
+            ));
+        }
+
+        lines
+            .map(|line| {
+                let (x, y) = line.split_once(' ')?;
+                Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(RunTrajectory)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed position"))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut contents = String::from(HEADER);
+        for position in &self.0 {
+            contents.push_str(&format!("\n{} {}", position.x, position.y));
+        }
+
+        fs::write(path, contents)
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Ties keep the older run, so its ghost doesn't change for nothing.
+pub fn is_new_best(ticks: usize, best_ticks: Option<usize>) -> bool {
+    best_ticks.is_none_or(|best| ticks < best)
+}
+
+#[derive(Resource, Default)]
+pub struct TrialRun {
+    running: bool,
+    finished: bool,
+    current: RunTrajectory,
+    best: Option<RunTrajectory>,
+}
+
+#[derive(Event, Debug)]
+pub struct TrialFinished {
+    pub ticks: usize,
+    pub best_ticks: Option<usize>,
+}
+
+#[derive(Component)]
+pub struct Ghost;
+
+fn trial_enabled(trial: Res<TimeTrial>) -> bool {
+    trial.target.is_some()
+}
+
+fn load_best_run(data_dir: Res<DataDir>, trial: Res<TimeTrial>, mut run: ResMut<TrialRun>) {
+    let Some(path) = trial.route_path(&data_dir.0) else {
+        return;
+    };
+
+    match RunTrajectory::load(&path) {
+        Ok(best) => {
+            info!("Best run for {:?}: {} ticks", path, best.ticks());
+            run.best = Some(best);
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => warn!("Ignoring ghost {:?}: {}", path, error),
+    }
+}
+
+fn start_run(
+    mut commands: Commands,
+    trial: Res<TimeTrial>,
+    mut run: ResMut<TrialRun>,
+    player: Query<(&Sprite, &Transform), With<Character>>,
+    current_level: CurrentLevel,
+) {
+    if run.running || run.finished {
+        return;
+    }
+
+    let Ok((sprite, transform)) = player.get_single() else {
+        return;
+    };
+
+    if current_level.identifier() != Some(trial.start.as_str()) {
+        return;
+    }
+
+    run.running = true;
+    run.current = RunTrajectory::default();
+    info!("Time trial started: {} -> {:?}", trial.start, trial.target);
+
+    if run.best.is_some() {
+        let mut ghost_sprite = sprite.clone();
+        ghost_sprite.color = ghost_sprite.color.with_alpha(GHOST_ALPHA);
+        commands.spawn((Ghost, ghost_sprite, *transform));
+    }
+}
+
+fn advance_run(mut run: ResMut<TrialRun>, player: Query<&Transform, With<Character>>) {
+    if !run.running {
+        return;
+    }
+
+    if let Ok(transform) = player.get_single() {
+        run.current.0.push(transform.translation.xy());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn finish_run(
+    mut commands: Commands,
+    data_dir: Res<DataDir>,
+    trial: Res<TimeTrial>,
+    time: Res<Time>,
+    mut run: ResMut<TrialRun>,
+    ghosts: Query<Entity, With<Ghost>>,
+    current_level: CurrentLevel,
+    mut finished: EventWriter<TrialFinished>,
+) {
+    if !run.running {
+        return;
+    }
+
+    if current_level.identifier() != trial.target.as_deref() {
+        return;
+    }
+
+    run.running = false;
+    run.finished = true;
+    for ghost in ghosts.iter() {
+        commands.entity(ghost).despawn_recursive();
+    }
+
+    let ticks = run.current.ticks();
+    let best_ticks = run.best.as_ref().map(RunTrajectory::ticks);
+    info!(
+        "Time trial finished in {:.2}s (best {:?})",
+        ticks as f32 * time.delta_secs(),
+        best_ticks.map(|best| best as f32 * time.delta_secs())
+    );
+    finished.send(TrialFinished { ticks, best_ticks });
+
+    if is_new_best(ticks, best_ticks) {
+        let Some(path) = trial.route_path(&data_dir.0) else {
+            return;
+        };
+
+        match run.current.save(&path) {
+            Ok(()) => info!("New best run saved to {:?}", path),
+            Err(error) => error!("Could not save run {:?}: {}", path, error),
+        }
+        run.best = Some(run.current.clone());
+    }
+}
+
+fn move_ghost(run: Res<TrialRun>, mut ghosts: Query<&mut Transform, With<Ghost>>) {
+    let Some(best) = &run.best else {
+        return;
+    };
+
+    // Ghost waits at the end of its run once the player is slower
+    let tick = run.current.ticks().saturating_sub(1);
+    let Some(position) = best.0.get(tick).or(best.0.last()) else {
+        return;
+    };
+
+    for mut transform in ghosts.iter_mut() {
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+fn reset_run_on_respawn(
+    mut commands: Commands,
+    mut restart: EventReader<RestartRequested>,
+    mut run: ResMut<TrialRun>,
+    ghosts: Query<Entity, With<Ghost>>,
+) {
+    if restart.read().count() > 0 {
+        run.running = false;
+        run.finished = false;
+        for ghost in ghosts.iter() {
+            commands.entity(ghost).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn trial_from_args() {
+        assert_eq!(TimeTrial::from_args(args("")).target, None);
+        assert_eq!(
+            TimeTrial::from_args(args("--replay run.replay --time-trial Boss_room")).target,
+            Some("Boss_room".to_string())
+        );
+        assert_eq!(TimeTrial::from_args(args("--time-trial")).target, None);
+        // The next flag is not a level name
+        assert_eq!(
+            TimeTrial::from_args(args("--time-trial --record run.replay")).target,
+            None
+        );
+    }
+
+    #[test]
+    fn route_path_names_both_levels() {
+        let trial = TimeTrial {
+            start: "Entrance".to_string(),
+            target: Some("Boss_room".to_string()),
+        };
+
+        assert_eq!(
+            trial.route_path(Path::new("data")),
+            Some(PathBuf::from("data/runs/Entrance__Boss_room.ghost"))
+        );
+        assert_eq!(TimeTrial::default().route_path(Path::new("data")), None);
+    }
+
+    #[test]
+    fn only_faster_runs_replace_the_best() {
+        assert!(is_new_best(300, None));
+        assert!(is_new_best(299, Some(300)));
+        assert!(!is_new_best(300, Some(300)));
+        assert!(!is_new_best(301, Some(300)));
+    }
+
+    #[test]
+    fn ghost_round_trips() {
+        let run = RunTrajectory(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.5, -2.25),
+            Vec2::new(-320.125, 64.0),
+        ]);
+        let path = std::env::temp_dir()
+            .join(format!("time-trial-{}", std::process::id()))
+            .join("route.ghost");
+
+        run.save(&path).unwrap();
+        let loaded = RunTrajectory::load(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded.unwrap().0, run.0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Headless harness: runs the gameplay plugins against an LDtk file without a
+//! window, feeding keyboard input frame by frame.
+
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use bevy::asset::AssetPlugin;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_playground::character::controller_kinematic::{Grounded, RenderInterpolation};
+use bevy_playground::character::Character;
+use bevy_playground::physics::PhysicsPlugin;
+use bevy_playground::save::DataDir;
+use bevy_playground::GamePlugin;
+
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+// Asset loading happens on background threads, so waiting is bounded by wall time
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_DATA_DIR: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestGame {
+    pub app: App,
+    data_dir: PathBuf,
+}
+
+impl TestGame {
+    pub fn new(ldtk_path: &'static str) -> Self {
+        // Saves, settings and runs go to a fresh directory so leftovers never leak into a test
+        let data_dir = std::env::temp_dir().join(format!(
+            "bevy_playground-{}-{}",
+            std::process::id(),
+            NEXT_DATA_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&data_dir).expect("could not create test data directory");
+
+        let mut app = App::new();
+
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(AssetPlugin::default())
+            .add_plugins(ImagePlugin::default_nearest())
+            .add_plugins(TransformPlugin)
+            .add_plugins(HierarchyPlugin)
+            .add_plugins(StatesPlugin)
+            .init_asset::<TextureAtlasLayout>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+            .insert_resource(DataDir(data_dir.clone()))
+            .add_plugins(PhysicsPlugin::default())
+            .insert_resource(LevelSelection::index(0))
+            .insert_resource(LdtkSettings {
+                level_background: LevelBackground::Nonexistent,
+                level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
+                    load_level_neighbors: false,
+                },
+                ..default()
+            })
+            .add_plugins(LdtkPlugin)
+            .add_plugins(GamePlugin)
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.spawn(LdtkWorldBundle {
+                        ldtk_handle: asset_server.load(ldtk_path).into(),
+                        ..Default::default()
+                    });
+                },
+            );
+
+        TestGame { app, data_dir }
+    }
+
+    /// Builds the game and steps until the player has been spawned.
+    pub fn loaded(ldtk_path: &'static str) -> Self {
+        let mut game = TestGame::new(ldtk_path);
+        assert!(
+            game.run_until(LOAD_TIMEOUT, |game| game.character_position().is_some()),
+            "player never spawned from {ldtk_path}"
+        );
+        game
+    }
+
+    pub fn press(&mut self, key: KeyCode) {
+        self.app
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(key);
+    }
+
+    pub fn release(&mut self, key: KeyCode) {
+        self.app
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .release(key);
+    }
+
+    /// Presses `key` for a single frame.
+    pub fn tap(&mut self, key: KeyCode) {
+        self.press(key);
+        self.step();
+        self.release(key);
+    }
+
+    /// Runs one frame. `just_pressed` and `just_released` only last for that frame.
+    pub fn step(&mut self) {
+        self.app.update();
+        self.app
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .clear();
+    }
+
+    pub fn steps(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Steps until `condition` holds, giving up after `timeout` of wall time.
+    pub fn run_until(&mut self, timeout: Duration, condition: impl Fn(&mut Self) -> bool) -> bool {
+        let start = Instant::now();
+
+        while start.elapsed() < timeout {
+            if condition(self) {
+                return true;
+            }
+            self.step();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        false
+    }
+
+    pub fn character_position(&mut self) -> Option<Vec2> {
+        self.app
+            .world_mut()
+            .query_filtered::<&Transform, With<Character>>()
+            .get_single(self.app.world())
+            .ok()
+            .map(|transform| transform.translation.xy())
+    }
+
+    pub fn player(&mut self) -> Option<Entity> {
+        self.app
+            .world_mut()
+            .query_filtered::<Entity, With<Character>>()
+            .get_single(self.app.world())
+            .ok()
+    }
+
+    /// Moves the player to `position` in world space, skipping interpolation.
+    pub fn teleport_player(&mut self, position: Vec2) {
+        let player = self.player().expect("no player to teleport");
+        self.app.world_mut().entity_mut(player).insert((
+            Transform::from_translation(position.extend(0.0)),
+            RenderInterpolation::default(),
+        ));
+    }
+
+    /// Level IIDs the LDtk world is spawning.
+    pub fn level_set(&mut self) -> Vec<String> {
+        self.app
+            .world_mut()
+            .query::<&LevelSet>()
+            .single(self.app.world())
+            .iids
+            .iter()
+            .map(|iid| iid.to_string())
+            .collect()
+    }
+
+    /// Whether the player is standing on something. False before it spawns.
+    pub fn grounded(&mut self) -> bool {
+        self.app
+            .world_mut()
+            .query_filtered::<(), (With<Character>, With<Grounded>)>()
+            .get_single(self.app.world())
+            .is_ok()
+    }
+
+    pub fn level_selection(&self) -> LevelSelection {
+        self.app.world().resource::<LevelSelection>().clone()
+    }
+
+    pub fn camera_translation(&mut self) -> Vec3 {
+        self.app
+            .world_mut()
+            .query_filtered::<&Transform, With<Camera2d>>()
+            .single(self.app.world())
+            .translation
+    }
+}
+
+impl Drop for TestGame {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.data_dir);
+    }
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_playground::character::Character;
+use bevy_playground::combat::projectile::{FireProjectile, ProjectileSpec, Team};
+use bevy_playground::health::{Died, Health};
+use common::TestGame;
+
+const TEST_LEVEL: &str = "test.ldtk";
+const GRIDVANIA: &str = "gridvania.ldtk";
+const ENTRANCE: &str = "a367c3b0-66b0-11ec-9cd7-91690c910c97";
+const SEWERS_TRASH: &str = "a36c5790-66b0-11ec-9cd7-0d08d7991930";
+
+#[test]
+fn player_spawns_in_first_level() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+
+    assert!(game.character_position().is_some());
+    assert_eq!(game.level_selection(), LevelSelection::index(0));
+}
+
+#[test]
+fn player_falls_onto_the_floor() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+    let spawn = game.character_position().unwrap();
+
+    game.steps(180);
+    let landed = game.character_position().unwrap();
+    game.steps(30);
+    let rested = game.character_position().unwrap();
+
+    assert!(
+        landed.y < spawn.y,
+        "player should fall from {spawn} but is at {landed}"
+    );
+    assert!(
+        (rested.y - landed.y).abs() < 1.0,
+        "player should rest on the floor"
+    );
+}
+
+#[test]
+fn player_lands_after_spawning() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+
+    assert!(!game.grounded(), "player should spawn in the air");
+    assert!(
+        game.run_until(Duration::from_secs(5), |game| game.grounded()),
+        "player never landed"
+    );
+}
+
+#[test]
+fn holding_right_moves_player_right() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+    game.steps(180);
+    let start = game.character_position().unwrap();
+
+    game.press(KeyCode::KeyD);
+    game.steps(30);
+    game.release(KeyCode::KeyD);
+
+    assert!(game.character_position().unwrap().x > start.x);
+}
+
+#[test]
+fn jumping_lifts_player() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+    game.steps(180);
+    let start = game.character_position().unwrap();
+
+    game.tap(KeyCode::Space);
+    game.steps(5);
+
+    assert!(game.character_position().unwrap().y > start.y);
+}
+
+#[test]
+fn enemy_bolt_damages_the_player() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+    game.steps(180);
+    let player = game.character_position().unwrap();
+    let health = |game: &mut TestGame| {
+        game.app
+            .world_mut()
+            .query_filtered::<&Health, With<Character>>()
+            .single(game.app.world())
+            .current
+    };
+    let full = health(&mut game);
+
+    // Both the player and the bolt are kinematic bodies
+    game.app.world_mut().send_event(FireProjectile {
+        owner: Entity::PLACEHOLDER,
+        team: Team::Enemy,
+        origin: player + Vec2::new(24.0, -4.0),
+        direction: Vec2::NEG_X,
+        spec: ProjectileSpec::ENEMY_BOLT,
+    });
+
+    assert!(
+        game.run_until(Duration::from_secs(2), |game| health(game) < full),
+        "bolt passed through the player"
+    );
+}
+
+#[test]
+fn dying_far_from_the_spawn_level_respawns_the_player() {
+    let mut game = TestGame::loaded(GRIDVANIA);
+
+    // Sewers_trash is four levels away, so streaming drops the Entrance
+    game.teleport_player(Vec2::new(512.0, -1792.0));
+    assert!(
+        game.run_until(Duration::from_secs(10), |game| {
+            game.level_selection() == LevelSelection::iid(SEWERS_TRASH)
+                && !game.level_set().iter().any(|iid| iid == ENTRANCE)
+        }),
+        "the Entrance never streamed out"
+    );
+
+    let player = game.player().unwrap();
+    game.app.world_mut().send_event(Died(player));
+
+    // Entrance spans x 0..512 and y -256..0 in world space
+    let in_entrance =
+        |position: Vec2| (0.0..512.0).contains(&position.x) && (-256.0..0.0).contains(&position.y);
+    assert!(
+        game.run_until(Duration::from_secs(10), |game| {
+            game.character_position().is_some_and(in_entrance)
+        }),
+        "player never respawned"
+    );
+    assert_eq!(game.level_selection(), LevelSelection::iid(ENTRANCE));
+}
+
+#[test]
+fn camera_stays_inside_level() {
+    let mut game = TestGame::loaded(TEST_LEVEL);
+    game.steps(120);
+
+    // test.ldtk is 640px wide and the default 1280px view at scale 0.3 is
+    // 384px wide, so the camera keeps 192px from each edge
+    let camera = game.camera_translation();
+    assert!((192.0..=448.0).contains(&camera.x), "camera at {camera}");
+}